@@ -0,0 +1,254 @@
+//! Arena Module
+//!
+//! This module provides a generational arena: a collection that hands out stable `Handle`s
+//! instead of `Vec` indices. Removing a value never moves the others, and each slot carries a
+//! generation that is bumped when its value is removed, so a handle to a removed value is
+//! detected as stale instead of silently pointing at whatever reuses the slot.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// A stable reference to a value stored in an `Arena<T>`.
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+// Handles are plain ids: implement the usual traits without requiring them on `T`.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+enum Slot<T> {
+    Occupied { generation: u32, value: T },
+    Vacant { generation: u32 },
+}
+
+/// A collection of values addressed by generational handles.
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Arena<T> {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Stores a value and returns its handle. Slots freed by `remove` are reused.
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            let Slot::Vacant { generation } = *slot else {
+                unreachable!("free list points at an occupied slot");
+            };
+            *slot = Slot::Occupied { generation, value };
+            return Handle {
+                index,
+                generation,
+                _marker: PhantomData,
+            };
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(Slot::Occupied {
+            generation: 0,
+            value,
+        });
+        Handle {
+            index,
+            generation: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Removes and returns the value behind a handle, or `None` if the handle is stale.
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        match slot {
+            Slot::Occupied { generation, .. } if *generation == handle.generation => {
+                let vacant = Slot::Vacant {
+                    generation: generation.wrapping_add(1),
+                };
+                let Slot::Occupied { value, .. } = std::mem::replace(slot, vacant) else {
+                    unreachable!();
+                };
+                self.free.push(handle.index);
+                self.len -= 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value behind a handle, or `None` if the handle is stale.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        match self.slots.get(handle.index as usize)? {
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value behind a handle mutably, or `None` if the handle is stale.
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index as usize)? {
+            Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of values stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether no values are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the stored values and their handles, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    Handle {
+                        index: index as u32,
+                        generation: *generation,
+                        _marker: PhantomData,
+                    },
+                    value,
+                )),
+                Slot::Vacant { .. } => None,
+            })
+    }
+
+    /// Iterates mutably over the stored values and their handles, in slot order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| match slot {
+                Slot::Occupied { generation, value } => Some((
+                    Handle {
+                        index: index as u32,
+                        generation: *generation,
+                        _marker: PhantomData,
+                    },
+                    value,
+                )),
+                Slot::Vacant { .. } => None,
+            })
+    }
+
+    /// Iterates over the stored values, in slot order.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Iterates mutably over the stored values, in slot order.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    /// Removes every value for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(Handle<T>, &mut T) -> bool) {
+        let removed: Vec<Handle<T>> = self
+            .iter_mut()
+            .filter_map(|(handle, value)| (!keep(handle, value)).then_some(handle))
+            .collect();
+        for handle in removed {
+            self.remove(handle);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Arena<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut arena = Self::new();
+        for value in iter {
+            arena.insert(value);
+        }
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_survive_removal_of_other_values() {
+        let mut arena: Arena<&str> = ["a", "b", "c"].into_iter().collect();
+        let handles = arena.iter().map(|(handle, _)| handle).collect::<Vec<_>>();
+        assert_eq!(arena.remove(handles[0]), Some("a"));
+        assert_eq!(arena.get(handles[1]), Some(&"b"));
+        assert_eq!(arena.get(handles[2]), Some(&"c"));
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn stale_handles_are_detected_after_slot_reuse() {
+        let mut arena = Arena::new();
+        let old = arena.insert(1);
+        arena.remove(old);
+        let new = arena.insert(2);
+        assert_ne!(old, new);
+        assert_eq!(arena.get(old), None);
+        assert_eq!(arena.get_mut(old), None);
+        assert_eq!(arena.remove(old), None);
+        assert_eq!(arena.get(new), Some(&2));
+    }
+
+    #[test]
+    fn retain_keeps_the_handles_of_the_survivors() {
+        let mut arena: Arena<i32> = (0..6).collect();
+        let handles = arena.iter().map(|(handle, _)| handle).collect::<Vec<_>>();
+        arena.retain(|_, value| *value % 2 == 0);
+        assert_eq!(arena.values().copied().collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(arena.get(handles[4]), Some(&4));
+        assert!(arena.get(handles[3]).is_none());
+    }
+}
@@ -2,14 +2,19 @@
 //!
 //! This module defines the baddie's behavior and properties.
 
+use crate::arena::{Arena, Handle};
+use crate::blocks::{Block, BlockState};
+use crate::body::Body;
 use crate::constants::{
     BADDIE_COLOR, BADDIE_ELEVATION_DROP_CHANCE, BADDIE_ELEVATION_SINE_AMPLITUDE,
     BADDIE_ELEVATION_SINE_FREQUENCY, BADDIE_ELEVATION_SPEED, BADDIE_ELEVATION_THRESHOLD,
-    BADDIE_JUMP_CHANCE, BADDIE_JUMP_FORCE, BADDIE_SIZE, BADDIE_SPEED, GRAVITY,
-    ITEM_THROW_OFFSET, ITEM_THROW_SPEED,
+    BADDIE_GRAB_CHANCE, BADDIE_GRAB_ITEM_CHANCE, BADDIE_JUMP_CHANCE, BADDIE_JUMP_FORCE,
+    BADDIE_MAX_GRAB_DURATION, BADDIE_MAX_ITEM_HOLD_DURATION, BADDIE_MIN_GRAB_DURATION,
+    BADDIE_MIN_ITEM_HOLD_DURATION, BADDIE_SIZE, BADDIE_SPEED, ITEM_THROW_OFFSET, ITEM_THROW_SPEED,
 };
 use crate::items::{Item, ItemState};
-use ::rand::{rng, Rng};
+use crate::physics::{Carry, Colliders, Contacts};
+use ::rand::Rng;
 use macroquad::prelude::*;
 
 /// Represents the different states a baddie can be in.
@@ -25,31 +30,27 @@ pub enum BaddieState {
 
 /// Represents a baddie character in the game.
 pub struct Baddie {
-    pub position: Vec2,
-    pub size: Vec2,
-    pub velocity: Vec2,
-    pub on_ground: bool,
+    pub body: Body,
     pub state: BaddieState,
     pub facing_right: bool,
     pub on_ground_frames: u32,
     pub elevation_x_axis: f32,
     pub elevation_time: f32,
-    pub grabbed_block_id: Option<usize>,
-    pub held_item_id: Option<usize>,
+    pub grabbed_block_id: Option<Handle<Block>>,
+    pub held_item_id: Option<Handle<Item>>,
     pub block_grab_timer: f32,
     pub item_hold_timer: f32,
 }
 
 impl Baddie {
-    /// Creates a new baddie instance at a given position.
-    pub fn new(pos: Vec2) -> Self {
+    /// Creates a new baddie instance at a given position, facing a random direction.
+    pub fn new(pos: Vec2, rng: &mut impl Rng) -> Self {
+        let mut body = Body::new(pos, vec2(BADDIE_SIZE, BADDIE_SIZE));
+        body.velocity.x = BADDIE_SPEED;
         Self {
-            position: pos,
-            size: vec2(BADDIE_SIZE, BADDIE_SIZE),
-            velocity: vec2(BADDIE_SPEED, 0.),
-            on_ground: false,
+            body,
             state: BaddieState::Run,
-            facing_right: rng().random_bool(0.5),
+            facing_right: rng.random_bool(0.5),
             on_ground_frames: 0,
             elevation_x_axis: 0.0,
             elevation_time: 0.0,
@@ -60,37 +61,49 @@ impl Baddie {
         }
     }
 
-    /// Returns the baddie's bounding box as a `Rect`.
-    pub fn rect(&self) -> Rect {
-        Rect::new(self.position.x, self.position.y, self.size.x, self.size.y)
+    /// Returns how far the held block or item, if any, sticks out in front of the baddie.
+    pub fn carry(&self, items: &Arena<Item>, blocks: &Arena<Block>) -> Carry {
+        let block_width = self
+            .grabbed_block_id
+            .and_then(|handle| blocks.get(handle))
+            .map_or(0.0, |b| b.body.size.x);
+        let item_width = self
+            .held_item_id
+            .and_then(|handle| items.get(handle))
+            .map_or(0.0, |i| i.body.size.x);
+        Carry {
+            width: block_width + item_width,
+            facing_right: self.facing_right,
+        }
     }
 
     /// Updates the baddie's state, including position, velocity, and state, based on physics.
-    pub fn update(&mut self, dt: f32) {
+    /// Random decisions (jumping, dropping held objects) are drawn from `rng`.
+    pub fn update(&mut self, dt: f32, rng: &mut impl Rng) {
         // Apply gravity to all states except Elevation
         if self.state != BaddieState::Elevation {
-            self.velocity.y += GRAVITY * dt;
+            self.body.apply_gravity(dt);
         }
 
         if self.state == BaddieState::Elevation {
-            self.velocity.y = BADDIE_ELEVATION_SPEED;
-            self.velocity.x = 0.0;
+            self.body.velocity.y = BADDIE_ELEVATION_SPEED;
+            self.body.velocity.x = 0.0;
             self.elevation_time += dt;
-            self.position.x = self.elevation_x_axis
+            self.body.position.x = self.elevation_x_axis
                 + (self.elevation_time * BADDIE_ELEVATION_SINE_FREQUENCY).sin()
                     * BADDIE_ELEVATION_SINE_AMPLITUDE;
 
-            if self.grabbed_block_id.is_some() || self.held_item_id.is_some() {
-                if rng().random_range(0.0..1.0) < BADDIE_ELEVATION_DROP_CHANCE {
-                    self.drop_held_object();
-                }
+            if (self.grabbed_block_id.is_some() || self.held_item_id.is_some())
+                && rng.random_range(0.0..1.0) < BADDIE_ELEVATION_DROP_CHANCE
+            {
+                self.drop_held_object();
             }
         } else if self.state == BaddieState::Grab {
-            self.velocity.x = if self.facing_right {
+            self.body.walk(if self.facing_right {
                 BADDIE_SPEED
             } else {
                 -BADDIE_SPEED
-            };
+            });
             self.block_grab_timer -= dt;
             if self.block_grab_timer <= 0.0 {
                 self.grabbed_block_id = None;
@@ -99,16 +112,15 @@ impl Baddie {
             }
         } else {
             // Set horizontal velocity based on direction
-            self.velocity.x = if self.facing_right {
+            self.body.walk(if self.facing_right {
                 BADDIE_SPEED
             } else {
                 -BADDIE_SPEED
-            };
+            });
 
             // Randomly jump if on the ground
-            if self.on_ground && rng().random_range(0.0..1.0) < BADDIE_JUMP_CHANCE {
-                self.velocity.y = -BADDIE_JUMP_FORCE;
-                self.on_ground = false;
+            if self.body.on_ground && rng.random_range(0.0..1.0) < BADDIE_JUMP_CHANCE {
+                self.body.jump(BADDIE_JUMP_FORCE);
             }
         }
 
@@ -117,12 +129,12 @@ impl Baddie {
         }
 
         // Update position
-        self.position += self.velocity * dt;
+        self.body.advance(dt);
 
         // Update state
-        if self.on_ground {
+        if self.body.on_ground {
             self.on_ground_frames += 1;
-            if self.velocity.x.abs() > 0.1 {
+            if self.body.velocity.x.abs() > 0.1 {
                 if self.state != BaddieState::Grab {
                     self.state = BaddieState::Run;
                 }
@@ -134,7 +146,7 @@ impl Baddie {
         } else {
             self.on_ground_frames = 0;
             if self.state != BaddieState::Elevation && self.state != BaddieState::Grab {
-                if self.velocity.y < 0. {
+                if self.body.velocity.y < 0. {
                     self.state = BaddieState::Jump;
                 } else {
                     self.state = BaddieState::Fall;
@@ -144,20 +156,14 @@ impl Baddie {
 
         if self.on_ground_frames > BADDIE_ELEVATION_THRESHOLD {
             self.state = BaddieState::Elevation;
-            self.elevation_x_axis = self.position.x;
+            self.elevation_x_axis = self.body.position.x;
             self.on_ground_frames = 0;
         }
     }
 
-    /// Draws the baddie on the screen.
-    pub fn draw(&self) {
-        draw_rectangle(
-            self.position.x,
-            self.position.y,
-            self.size.x,
-            self.size.y,
-            BADDIE_COLOR,
-        );
+    /// Draws the baddie on the screen, interpolated by `alpha` between its last two positions.
+    pub fn draw(&self, alpha: f32) {
+        self.body.draw(alpha, BADDIE_COLOR);
     }
 
     pub fn drop_held_object(&mut self) {
@@ -175,34 +181,35 @@ impl Baddie {
     }
 
     /// Handles baddie interactions with items (grabbing, throwing).
-    pub fn process_interactions(&mut self, items: &mut [Item], player_pos: Vec2) {
+    pub fn process_interactions(&mut self, items: &mut Arena<Item>, player_pos: Vec2) {
         if let Some(item_id) = self.held_item_id {
-            // First, ensure the item ID is valid. If not, the item has been removed,
+            // First, ensure the handle is still live. If not, the item has been removed,
             // so the baddie should drop its reference to it.
-            if item_id >= items.len() {
+            let Some(item) = items.get_mut(item_id) else {
                 self.held_item_id = None;
                 return;
-            }
+            };
 
             // Check if the hold timer has elapsed.
             if self.item_hold_timer <= 0.0 {
                 // If the timer is up, check if the baddie is facing the player.
-                let player_is_to_right = player_pos.x > self.position.x;
+                let player_is_to_right = player_pos.x > self.body.position.x;
                 let baddie_is_facing_player = self.facing_right == player_is_to_right;
 
                 if baddie_is_facing_player {
                     // If both conditions are met, throw the item.
-                    let item = &mut items[item_id];
                     item.state = ItemState::Thrown;
-                    item.on_ground = false;
+                    item.body.on_ground = false;
                     let dir = if self.facing_right { 1.0 } else { -1.0 };
-                    item.velocity = self.velocity + vec2(dir, -1.0).normalize() * ITEM_THROW_SPEED;
+                    item.body.velocity =
+                        self.body.velocity + vec2(dir, -1.0).normalize() * ITEM_THROW_SPEED;
 
                     // Offset the item's starting position to avoid immediate self-collision
                     if self.facing_right {
-                        item.position.x = self.rect().right() + ITEM_THROW_OFFSET;
+                        item.body.position.x = self.body.rect().right() + ITEM_THROW_OFFSET;
                     } else {
-                        item.position.x = self.rect().left() - item.size.x - ITEM_THROW_OFFSET;
+                        item.body.position.x =
+                            self.body.rect().left() - item.body.size.x - ITEM_THROW_OFFSET;
                     }
 
                     self.held_item_id = None;
@@ -212,13 +219,95 @@ impl Baddie {
 
             // If the item wasn't thrown (either because the timer isn't up or the
             // baddie isn't facing the player), keep it hooked.
-            let item = &mut items[item_id];
-            item.position.y = self.position.y;
-            item.position.x = if self.facing_right {
-                self.position.x + self.size.x
+            item.body.position.y = self.body.position.y;
+            item.body.position.x = if self.facing_right {
+                self.body.position.x + self.body.size.x
             } else {
-                self.position.x - item.size.x
+                self.body.position.x - item.body.size.x
             };
         }
     }
-}
\ No newline at end of file
+
+    /// Reacts to what the baddie touched while moving: it turns around at walls and blocks (or
+    /// randomly grabs the block), grabs items it walks over and sometimes turns back at edges.
+    /// The random decisions are drawn from `rng`. A grabbed block stops being solid in
+    /// `colliders`.
+    pub fn react_to_contacts(
+        &mut self,
+        contacts: &Contacts,
+        blocks: &mut Arena<Block>,
+        items: &mut Arena<Item>,
+        colliders: &mut Colliders,
+        rng: &mut impl Rng,
+    ) {
+        // --- Baddie vs. Walls ---
+        // Reverse direction upon hitting a wall.
+        if contacts.wall.is_some() {
+            self.change_direction();
+        }
+
+        // --- Baddie vs. Ceiling ---
+        if self.state == BaddieState::Elevation {
+            if contacts.ceiling {
+                self.state = BaddieState::Idle;
+                self.body.velocity.y = 0.;
+                self.drop_held_object();
+            }
+            return; // No other reactions while elevating
+        }
+
+        // --- Baddie vs. Blocks ---
+        // Bumping into a block either grabs it or turns the baddie around.
+        if let Some((handle, _)) = contacts.block {
+            if self.held_item_id.is_none()
+                && self.grabbed_block_id.is_none()
+                && rng.random_range(0.0..1.0) < BADDIE_GRAB_CHANCE
+                && let Some(block) = blocks.get_mut(handle)
+            {
+                self.state = BaddieState::Grab;
+                self.grabbed_block_id = Some(handle);
+                self.block_grab_timer =
+                    rng.random_range(BADDIE_MIN_GRAB_DURATION..BADDIE_MAX_GRAB_DURATION);
+                block.state = BlockState::Hooked;
+                colliders.remove_block(handle);
+            } else {
+                self.change_direction();
+            }
+        }
+
+        // --- Baddie vs. Items ---
+        if self.held_item_id.is_none() && self.grabbed_block_id.is_none() {
+            for (handle, item) in items.iter_mut() {
+                if item.body_options().holdable
+                    && item.body.on_ground
+                    && self.body.rect().overlaps(&item.body.rect())
+                    && rng.random_range(0.0..1.0) < BADDIE_GRAB_ITEM_CHANCE
+                {
+                    item.state = ItemState::Hooked;
+                    self.held_item_id = Some(handle);
+                    self.item_hold_timer = rng
+                        .random_range(BADDIE_MIN_ITEM_HOLD_DURATION..BADDIE_MAX_ITEM_HOLD_DURATION);
+                    break;
+                }
+            }
+        }
+
+        // --- Edge Detection ---
+        // Check if the baddie is about to fall off a platform or block.
+        if self.body.on_ground {
+            // Create a probe point just ahead of and below the baddie to check for ground.
+            let rect = self.body.rect();
+            let probe_x = if self.facing_right {
+                rect.right()
+            } else {
+                rect.left()
+            };
+            let probe_point = vec2(probe_x, rect.bottom() + 1.0);
+
+            // If there is no ground ahead, randomly decide whether to change direction or fall.
+            if !colliders.supports(probe_point) && rng.random_bool(0.1) {
+                self.change_direction();
+            }
+        }
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! This module defines the blocks that appear in the game world.
 
-use crate::constants::{BLOCK_SIZE, GRAVITY};
+use crate::body::{Body, BodyOptions};
+use crate::constants::BLOCK_SIZE;
 use macroquad::prelude::*;
 
 /// Represents the state of a block.
@@ -10,15 +11,14 @@ use macroquad::prelude::*;
 pub enum BlockState {
     Idle,
     Hooked,
+    /// Flying after being thrown, until it lands.
+    Thrown,
 }
 
 /// Represents a block in the game world.
 #[derive(Clone)]
 pub struct Block {
-    pub position: Vec2,
-    pub size: Vec2,
-    pub velocity: Vec2,
-    pub on_ground: bool,
+    pub body: Body,
     pub state: BlockState,
 }
 
@@ -26,40 +26,38 @@ impl Block {
     /// Creates a new block at a specific position.
     pub fn new(pos: Vec2) -> Self {
         Self {
-            position: pos,
-            size: vec2(BLOCK_SIZE, BLOCK_SIZE),
-            velocity: Vec2::ZERO,
-            on_ground: false,
+            body: Body::new(pos, vec2(BLOCK_SIZE, BLOCK_SIZE)),
             state: BlockState::Idle,
         }
     }
 
-    /// Returns the block's bounding box as a `Rect`.
-    pub fn rect(&self) -> Rect {
-        Rect::new(self.position.x, self.position.y, self.size.x, self.size.y)
+    /// Returns how the collision pipeline treats the block: it stops dead when it lands, slides
+    /// down slopes, and it can only be picked up while it lies idle.
+    pub fn body_options(&self) -> BodyOptions {
+        BodyOptions {
+            bounce: 0.0,
+            min_bounce_speed: 0.0,
+            friction: 1.0,
+            holdable: self.state == BlockState::Idle,
+            slides: true,
+        }
     }
 
     /// Updates the block's state, applying gravity only if it's not on the ground.
     pub fn update(&mut self, dt: f32) {
-        if !self.on_ground {
-            self.velocity.y += GRAVITY * dt;
-            self.position += self.velocity * dt;
+        if !self.body.on_ground {
+            self.body.apply_gravity(dt);
+            self.body.advance(dt);
         }
     }
 
-    /// Draws the block on the screen.
-    pub fn draw(&self) {
-        let color = if self.state == BlockState::Hooked {
-            YELLOW
-        } else {
-            ORANGE
+    /// Draws the block on the screen, interpolated by `alpha` between its last two positions.
+    pub fn draw(&self, alpha: f32) {
+        let color = match self.state {
+            BlockState::Idle => ORANGE,
+            BlockState::Hooked => YELLOW,
+            BlockState::Thrown => RED,
         };
-        draw_rectangle(
-            self.position.x,
-            self.position.y,
-            self.size.x,
-            self.size.y,
-            color,
-        );
+        self.body.draw(alpha, color);
     }
 }
@@ -0,0 +1,140 @@
+//! Body Module
+//!
+//! This module defines the physical body shared by every moving object in the game (the player,
+//! baddies, items and blocks), along with the per-kind options that tune how the collision
+//! pipeline in `physics.rs` treats it.
+
+use crate::arena::Handle;
+use crate::blocks::Block;
+use crate::constants::GRAVITY;
+use crate::physics;
+use macroquad::prelude::*;
+
+/// How the collision pipeline treats a body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyOptions {
+    /// The fraction of its speed a body keeps when it rebounds off a surface. Zero stops it dead.
+    pub bounce: f32,
+    /// The speed under which a bouncing body comes to rest on the floor instead of rebounding.
+    pub min_bounce_speed: f32,
+    /// The fraction of its horizontal speed a body loses when it touches the floor.
+    pub friction: f32,
+    /// Whether the body can currently be picked up.
+    pub holdable: bool,
+    /// Whether the body slides down slopes instead of standing on them.
+    pub slides: bool,
+}
+
+impl BodyOptions {
+    /// A body that stops on contact, keeps its horizontal speed on the floor and stands on slopes,
+    /// as characters do.
+    pub const CHARACTER: Self = Self {
+        bounce: 0.0,
+        min_bounce_speed: 0.0,
+        friction: 0.0,
+        holdable: false,
+        slides: false,
+    };
+}
+
+/// The position, size and motion of a simulated object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+    pub position: Vec2,
+    /// The position at the start of the last simulation step, used to interpolate rendering.
+    pub previous_position: Vec2,
+    pub size: Vec2,
+    pub velocity: Vec2,
+    pub on_ground: bool,
+    /// The block the body came to rest on, if any. A body whose block is picked up, moved or
+    /// removed is woken up so that it falls.
+    pub resting_on: Option<Handle<Block>>,
+    /// The one-way platform the body is dropping through, ignored until the body has cleared it.
+    pub dropping_through: Option<usize>,
+    /// The velocity of the moving platform the body rides. A body that leaves the platform keeps
+    /// it until it lands again.
+    pub platform_velocity: Vec2,
+}
+
+impl Body {
+    /// Creates a body at rest.
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            position,
+            previous_position: position,
+            size,
+            velocity: Vec2::ZERO,
+            on_ground: false,
+            resting_on: None,
+            dropping_through: None,
+            platform_velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Returns the body's bounding box as a `Rect`.
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.position.x, self.position.y, self.size.x, self.size.y)
+    }
+
+    /// Returns the body's position interpolated between the last two simulation steps.
+    pub fn render_position(&self, alpha: f32) -> Vec2 {
+        self.previous_position.lerp(self.position, alpha)
+    }
+
+    /// Returns whether this body and `other` overlapped at any point of the last step, however
+    /// fast they moved. This catches a fast body that went through the other in a single step.
+    pub fn touched_during_step(&self, other: &Body) -> bool {
+        let motion =
+            (self.position - self.previous_position) - (other.position - other.previous_position);
+        let start = Rect::new(
+            self.previous_position.x,
+            self.previous_position.y,
+            self.size.x,
+            self.size.y,
+        );
+        let other_start = Rect::new(
+            other.previous_position.x,
+            other.previous_position.y,
+            other.size.x,
+            other.size.y,
+        );
+        physics::sweep_overlaps(&start, motion, &other_start)
+    }
+
+    /// Remembers the current position as the start of the next simulation step.
+    pub fn store_previous_position(&mut self) {
+        self.previous_position = self.position;
+    }
+
+    /// Accelerates the body downwards for `dt` seconds.
+    pub fn apply_gravity(&mut self, dt: f32) {
+        self.velocity.y += GRAVITY * dt;
+    }
+
+    /// Sets the body's horizontal speed. An airborne body adds the speed of the moving platform
+    /// it left; a body standing on a moving platform is carried by the platform instead.
+    pub fn walk(&mut self, speed: f32) {
+        self.velocity.x = if self.on_ground {
+            speed
+        } else {
+            speed + self.platform_velocity.x
+        };
+    }
+
+    /// Makes the body leave the ground upwards, along with the moving platform it stands on.
+    pub fn jump(&mut self, force: f32) {
+        self.velocity.y = self.platform_velocity.y - force;
+        self.on_ground = false;
+    }
+
+    /// Moves the body along its velocity for `dt` seconds.
+    pub fn advance(&mut self, dt: f32) {
+        self.position += self.velocity * dt;
+    }
+
+    /// Draws the body as a rectangle, interpolated by `alpha` between its last two positions.
+    pub fn draw(&self, alpha: f32, color: Color) {
+        let position = self.render_position(alpha);
+        draw_rectangle(position.x, position.y, self.size.x, self.size.y, color);
+    }
+}
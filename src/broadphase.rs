@@ -0,0 +1,185 @@
+//! Broadphase Module
+//!
+//! This module provides `SpatialGrid`, a uniform grid that buckets rectangles by the cells they
+//! cover. Finding what a body may touch then only looks at the few cells around the body
+//! instead of at every collider in the level.
+
+use macroquad::prelude::Rect;
+use std::collections::HashMap;
+
+type Cell = (i32, i32);
+
+/// A uniform grid of rectangles, each tagged with an id.
+pub struct SpatialGrid<T> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<(T, Rect)>>,
+}
+
+impl<T: Copy + PartialEq> SpatialGrid<T> {
+    /// Creates an empty grid with square cells of the given side.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    /// Removes every rectangle, keeping the cells' storage for the next rebuild.
+    pub fn clear(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    /// Returns the cell containing a point.
+    fn cell(&self, x: f32, y: f32) -> Cell {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// Returns the first and last cells covered by a rectangle, edges included.
+    fn cell_range(&self, rect: &Rect) -> (Cell, Cell) {
+        (
+            self.cell(rect.left(), rect.top()),
+            self.cell(rect.right(), rect.bottom()),
+        )
+    }
+
+    /// Adds a rectangle to every cell it covers.
+    pub fn insert(&mut self, id: T, rect: Rect) {
+        let ((x0, y0), (x1, y1)) = self.cell_range(&rect);
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                self.cells.entry((x, y)).or_default().push((id, rect));
+            }
+        }
+    }
+
+    /// Removes a rectangle. `rect` must be the rectangle it was inserted with.
+    pub fn remove(&mut self, id: T, rect: Rect) {
+        let ((x0, y0), (x1, y1)) = self.cell_range(&rect);
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                if let Some(entries) = self.cells.get_mut(&(x, y)) {
+                    entries.retain(|(other, _)| *other != id);
+                }
+            }
+        }
+    }
+
+    /// Iterates over the rectangles overlapping `area`, edges included. Each one is reported
+    /// once, even if it shares several cells with `area`.
+    pub fn query(&self, area: Rect) -> impl Iterator<Item = (T, Rect)> + '_ {
+        let ((x0, y0), (x1, y1)) = self.cell_range(&area);
+        (x0..=x1)
+            .flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+            .filter_map(|cell| Some((cell, self.cells.get(&cell)?)))
+            .flat_map(move |(cell, entries)| {
+                entries.iter().filter(move |(_, rect)| {
+                    // A rectangle is only reported from the first cell it shares with `area`.
+                    let (ex, ey) = self.cell(rect.left(), rect.top());
+                    cell == (ex.max(x0), ey.max(y0)) && rect.overlaps(&area)
+                })
+            })
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::rngs::StdRng;
+    use ::rand::{Rng, SeedableRng};
+    use std::time::Instant;
+
+    #[test]
+    fn query_reports_each_overlapping_rect_once() {
+        let mut grid = SpatialGrid::new(100.0);
+        grid.insert(1, Rect::new(50., 50., 300., 20.));
+        grid.insert(2, Rect::new(500., 500., 10., 10.));
+        grid.insert(3, Rect::new(0., 0., 10., 10.));
+
+        let found: Vec<i32> = grid
+            .query(Rect::new(40., 40., 250., 50.))
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(found, vec![1]);
+
+        let found: Vec<i32> = grid
+            .query(Rect::new(-20., -20., 100., 100.))
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&1) && found.contains(&3));
+    }
+
+    #[test]
+    fn removed_rects_are_no_longer_found() {
+        let mut grid = SpatialGrid::new(100.0);
+        let rect = Rect::new(90., 90., 60., 60.);
+        grid.insert(7, rect);
+        grid.remove(7, rect);
+        assert_eq!(grid.query(rect).count(), 0);
+    }
+
+    /// Compares the grid with a brute-force scan over hundreds of blocks and bodies, printing
+    /// the timings of both. Run with
+    /// `cargo test --release benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark_grid_against_brute_force() {
+        const BLOCKS: usize = 800;
+        const BODIES: usize = 400;
+        const STEPS: usize = 200;
+
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut random_rect = |size: f32| {
+            Rect::new(
+                rng.random_range(0.0..2048.0),
+                rng.random_range(0.0..1536.0),
+                size,
+                size,
+            )
+        };
+        let blocks: Vec<Rect> = (0..BLOCKS).map(|_| random_rect(60.)).collect();
+        let bodies: Vec<Rect> = (0..BODIES).map(|_| random_rect(40.)).collect();
+
+        let start = Instant::now();
+        let mut brute_force_hits = 0;
+        for _ in 0..STEPS {
+            for body in &bodies {
+                brute_force_hits += blocks.iter().filter(|block| block.overlaps(body)).count();
+            }
+        }
+        let brute_force = start.elapsed();
+
+        let start = Instant::now();
+        let mut grid_hits = 0;
+        let mut grid = SpatialGrid::new(crate::constants::GRID_CELL_SIZE);
+        for _ in 0..STEPS {
+            // The grid is rebuilt every step, as the world does.
+            grid.clear();
+            for (i, block) in blocks.iter().enumerate() {
+                grid.insert(i, *block);
+            }
+            for body in &bodies {
+                grid_hits += grid.query(*body).count();
+            }
+        }
+        let grid_time = start.elapsed();
+
+        println!(
+            "{} bodies against {} blocks, {} steps: brute force {:?}, grid {:?} ({:.1}x)",
+            BODIES,
+            BLOCKS,
+            STEPS,
+            brute_force,
+            grid_time,
+            brute_force.as_secs_f64() / grid_time.as_secs_f64()
+        );
+        // Timings vary with the machine's load, so only the results are checked.
+        assert_eq!(brute_force_hits, grid_hits);
+    }
+}
@@ -1,35 +1,36 @@
 //! Camera Module
 //!
-//! This module defines the camera that follows the player.
+//! This module defines the camera that follows the player, or is panned around the level in
+//! the editor.
 
 use crate::constants::{SCREEN_QUARTER_HEIGHT_FACTOR, SCREEN_QUARTER_WIDTH_FACTOR};
-use crate::level::{LEVEL_HEIGHT, LEVEL_WIDTH};
-use crate::player::Player;
 use macroquad::prelude::*;
 
 /// Represents the game camera, which follows the player.
 pub struct Camera {
     pub rect: Rect,
+    /// The width and height of the level the camera is kept inside.
+    level_size: Vec2,
 }
 
 impl Camera {
-    /// Creates a new camera instance.
-    pub fn new() -> Self {
+    /// Creates a new camera showing the bottom-left corner of a level of size `level_size`.
+    pub fn new(level_size: Vec2) -> Self {
         Self {
             rect: Rect::new(
                 0.,
-                LEVEL_HEIGHT - screen_height(),
+                level_size.y - screen_height(),
                 screen_width(),
                 screen_height(),
             ),
+            level_size,
         }
     }
 
-    /// Updates the camera's position to follow the player, clamping it to the level bounds.
-    pub fn update(&mut self, player: &Player) {
+    /// Updates the camera's position to follow the player's (rendered) bounding box, clamping it to the level bounds.
+    pub fn update(&mut self, player_rect: Rect) {
         let screen_quarter_w = screen_width() * SCREEN_QUARTER_WIDTH_FACTOR;
         let screen_quarter_h = screen_height() * SCREEN_QUARTER_HEIGHT_FACTOR;
-        let player_rect = player.rect();
 
         // Scroll left
         if player_rect.x < self.rect.x - screen_quarter_w {
@@ -37,7 +38,7 @@ impl Camera {
         }
         // Scroll right
         if player_rect.right() > self.rect.right() + screen_quarter_w
-            && self.rect.right() < LEVEL_WIDTH
+            && self.rect.right() < self.level_size.x
         {
             self.rect.x = player_rect.right() - self.rect.w - screen_quarter_w;
         }
@@ -47,13 +48,32 @@ impl Camera {
         }
         // Scroll down
         if player_rect.bottom() > self.rect.bottom() - screen_quarter_h
-            && self.rect.bottom() < LEVEL_HEIGHT
+            && self.rect.bottom() < self.level_size.y
         {
             self.rect.y = player_rect.bottom() - self.rect.h + screen_quarter_h;
         }
 
-        // Clamp camera to level bounds
-        self.rect.x = self.rect.x.max(0.).min(LEVEL_WIDTH - self.rect.w);
-        self.rect.y = self.rect.y.max(0.).min(LEVEL_HEIGHT - self.rect.h);
+        self.clamp();
+    }
+
+    /// Moves the camera by `delta`, as far as the level bounds allow.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.rect = self.rect.offset(delta);
+        self.clamp();
+    }
+
+    /// Returns the macroquad camera that draws the part of the level the camera shows.
+    pub fn camera_2d(&self) -> Camera2D {
+        Camera2D {
+            target: self.rect.center(),
+            zoom: vec2(1. / self.rect.w, 1. / self.rect.h),
+            ..Default::default()
+        }
+    }
+
+    /// Clamps the camera to the level bounds.
+    fn clamp(&mut self) {
+        self.rect.x = self.rect.x.max(0.).min(self.level_size.x - self.rect.w);
+        self.rect.y = self.rect.y.max(0.).min(self.level_size.y - self.rect.h);
     }
 }
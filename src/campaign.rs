@@ -0,0 +1,39 @@
+//! Campaign Module
+//!
+//! This module lists the levels of a run, in the order they are played.
+
+use crate::level_file::LevelFile;
+
+/// A level of the campaign.
+pub struct CampaignLevel {
+    /// The name shown when the level is complete.
+    pub name: &'static str,
+    /// The contents of the level's file, from `assets/levels`.
+    pub source: &'static str,
+}
+
+impl CampaignLevel {
+    /// Parses the level's file. The files of the campaign are checked by the tests, so an
+    /// invalid one is a bug.
+    pub fn level_file(&self) -> LevelFile {
+        LevelFile::parse(self.source)
+            .unwrap_or_else(|err| panic!("invalid level file for {}: {}", self.name, err))
+    }
+}
+
+/// The levels of a run, each one with more baddies than the one before. The run is won once
+/// the last one is complete.
+pub const CAMPAIGN: &[CampaignLevel] = &[
+    CampaignLevel {
+        name: "Meadow",
+        source: include_str!("../assets/levels/meadow.ron"),
+    },
+    CampaignLevel {
+        name: "Thicket",
+        source: include_str!("../assets/levels/thicket.ron"),
+    },
+    CampaignLevel {
+        name: "Stronghold",
+        source: include_str!("../assets/levels/stronghold.ron"),
+    },
+];
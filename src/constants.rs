@@ -4,31 +4,74 @@
 
 use macroquad::prelude::Color;
 
+/// The number of fixed simulation steps per second.
+pub const PHYSICS_STEP_RATE: f32 = 120.0;
+/// The maximum number of simulation steps run in a single frame before the remaining time is dropped.
+pub const MAX_PHYSICS_STEPS_PER_FRAME: u32 = 8;
+/// The side of a cell of the collision broadphase grid, a little larger than a block.
+pub const GRID_CELL_SIZE: f32 = 128.0;
+/// How far, in pixels, a character walking down a slope is pulled down to stay on it.
+pub const SLOPE_SNAP_DISTANCE: f32 = 16.0;
+/// The file the key bindings are loaded from and saved to.
+pub const CONTROLS_CONFIG_PATH: &str = "controls.cfg";
+/// The file the high scores are saved to, in the game's directory of the user's data directory.
+pub const HIGH_SCORES_FILE_NAME: &str = "highscores.txt";
+/// How many runs the high-score table keeps.
+pub const MAX_HIGH_SCORES: usize = 10;
+/// The longest name that can be entered in the high-score table.
+pub const HIGH_SCORE_NAME_MAX_LEN: usize = 12;
 pub const PLAYER_SIZE: f32 = 50.;
 pub const PLAYER_SPEED: f32 = 500.;
 pub const JUMP_FORCE: f32 = 600.;
 pub const GRAVITY: f32 = 1000.;
-pub const PLAYER_SPAWN_X: f32 = 100.0;
-pub const PLAYER_SAFE_ZONE_MULTIPLIER: f32 = 3.0;
+/// How many lives the player starts a run with.
+pub const PLAYER_LIVES: u32 = 3;
+/// How many hits the player takes before losing a life.
+pub const PLAYER_MAX_HEALTH: u32 = 3;
+/// How long, in seconds, the player cannot be hurt again after a hit or a respawn.
+pub const PLAYER_INVULNERABILITY_DURATION: f32 = 2.0;
+/// How long, in seconds, a hit knocks the player back, without any control over it.
+pub const PLAYER_KNOCKBACK_DURATION: f32 = 0.3;
+/// The horizontal and upward speed a hit knocks the player back with, away from what hit them.
+pub const PLAYER_KNOCKBACK_SPEED: f32 = 300.;
+/// How long, in seconds, the player stays visible or hidden while blinking.
+pub const PLAYER_BLINK_INTERVAL: f32 = 0.1;
 pub const SCREEN_QUARTER_WIDTH_FACTOR: f32 = 0.25;
 pub const SCREEN_QUARTER_HEIGHT_FACTOR: f32 = 0.25;
-pub const GROUND_HEIGHT: f32 = 50.;
-pub const CEILING_HEIGHT: f32 = 50.;
-pub const WALL_WIDTH: f32 = 50.;
 pub const ITEM_SIZE: f32 = 25.0;
-pub const ITEM_COUNT: usize = 8;
 pub const ITEM_THROW_SPEED: f32 = 600.0;
 /// The small distance to offset a thrown item from the thrower to prevent immediate self-collision.
 pub const ITEM_THROW_OFFSET: f32 = 1.0;
 pub const ITEM_BOUNCE_ENERGY_LOSS: f32 = 0.7;
 pub const ITEM_MIN_BOUNCE_SPEED: f32 = 60.0;
+pub const MOVING_PLATFORM_SPEED: f32 = 100.;
+/// How long, in seconds, a moving platform waits at each end of its path.
+pub const MOVING_PLATFORM_WAIT: f32 = 1.0;
+pub const CHECKPOINT_WIDTH: f32 = 20.;
+pub const DOOR_WIDTH: f32 = 50.;
+pub const DOOR_HEIGHT: f32 = 80.;
+pub const CHECKPOINT_HEIGHT: f32 = 60.;
 pub const BLOCK_SIZE: f32 = PLAYER_SIZE * 1.2;
-pub const BLOCK_COUNT: usize = 27;
+/// The side of a cell of an ASCII level grid: each character covers one block.
+pub const LEVEL_GRID_CELL_SIZE: f32 = BLOCK_SIZE;
+/// The thickness of a one-way platform drawn with `=` in an ASCII level grid, at the top of its
+/// cells.
+pub const LEVEL_GRID_PLATFORM_HEIGHT: f32 = 20.;
+/// The tileset the tiles of Tiled maps are drawn from: 16 by 16 tiles of 16 pixels.
+pub const TILESET_PATH: &str = "assets/sprites/world_tileset.png";
+pub const TILESET_TILE_SIZE: f32 = 16.;
+pub const TILESET_COLUMNS: u32 = 16;
+pub const TILESET_TILE_COUNT: u32 = 256;
 pub const BLOCK_OFFSET: f32 = 20.;
+/// How fast the player pushes a single block. A stack of blocks goes slower.
+pub const BLOCK_PUSH_SPEED: f32 = PLAYER_SPEED * 0.4;
+/// How heavy a block is compared with an item. A block gets the same throw impulse as an item,
+/// so it leaves the hand that many times slower and flies a shorter arc.
+pub const BLOCK_MASS: f32 = 1.5;
 /// The small distance to offset a held block from the baddy to prevent immediate self-collision.
 pub const HELD_BLOCK_OFFSET: f32 = 1.0;
-pub const MAX_BADDIES: usize = 8;
 pub const BADDIE_SIZE: f32 = PLAYER_SIZE * 0.8;
+pub const KEY_SIZE: f32 = PLAYER_SIZE * 1.2;
 pub const BADDIE_SPEED: f32 = 200.;
 pub const BADDIE_JUMP_FORCE: f32 = 600.;
 pub const BADDIE_JUMP_CHANCE: f32 = 0.005; // Roughly once every 20 seconds at 60fps
@@ -44,13 +87,45 @@ pub const BADDIE_MIN_ITEM_HOLD_DURATION: f32 = 1.0;
 pub const BADDIE_MAX_ITEM_HOLD_DURATION: f32 = 8.0;
 pub const BADDIE_GRAB_ITEM_CHANCE: f32 = 0.6;
 
+// --- Scoring
+/// The points for a baddie killed with a thrown object. The nth kill of a multi-kill is worth
+/// n times as much.
+pub const KILL_POINTS: u32 = 100;
+/// How long, in seconds, after a kill the next one still counts towards a multi-kill.
+pub const MULTI_KILL_WINDOW: f32 = 1.5;
+pub const KEY_POINTS: u32 = 500;
+/// The bonus for finishing a level instantly. It shrinks every second spent in the level.
+pub const TIME_BONUS: u32 = 5000;
+pub const TIME_BONUS_PER_SECOND: u32 = 20;
+/// The bonus for every life left when a level is finished.
+pub const LIFE_BONUS: u32 = 1000;
+
+// --- Editor
+/// The file a level edited in the editor is saved to, unless it was loaded from a file.
+pub const EDITOR_LEVEL_PATH: &str = "level.ron";
+/// The step of the grid that what is placed in the editor snaps to, in pixels.
+pub const EDITOR_SNAP: f32 = 10.;
+/// How many edits can be undone.
+pub const EDITOR_HISTORY_LIMIT: usize = 100;
+/// How fast the arrow keys pan the editor's camera, in pixels per second.
+pub const EDITOR_PAN_SPEED: f32 = 800.;
+/// The side of the handle at the bottom-right corner of a selected platform that resizes it.
+pub const EDITOR_HANDLE_SIZE: f32 = 12.;
+
 // --- Colors
 pub const PLAYER_COLOR: Color = Color::new(1.0, 1.0, 1.0, 1.0); // WHITE
 pub const BADDIE_COLOR: Color = Color::new(0.5, 0.5, 1.0, 1.0); // Light Blue
 pub const BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 1.0); // BLACK
 pub const BOUNDS_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0); // YELLOW
 pub const PLATFORM_COLOR: Color = Color::new(0.0, 1.0, 0.0, 1.0); // GREEN
+pub const SOLID_PLATFORM_COLOR: Color = Color::new(0.0, 0.6, 0.0, 1.0); // Dark Green
 pub const ITEM_IDLE_COLOR: Color = Color::new(0.0, 0.0, 1.0, 1.0); // BLUE
 pub const ITEM_HOOKED_COLOR: Color = Color::new(1.0, 1.0, 0.0, 1.0); // YELLOW
 pub const ITEM_THROWN_COLOR: Color = Color::new(1.0, 0.0, 0.0, 1.0); // RED
 pub const KEY_COLOR: Color = Color::new(0.5, 1.0, 0.5, 1.0); // Light Green
+pub const CHECKPOINT_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0); // GRAY
+pub const DOOR_LOCKED_COLOR: Color = Color::new(0.4, 0.2, 0.0, 1.0); // Brown
+pub const DOOR_OPEN_COLOR: Color = Color::new(1.0, 0.84, 0.0, 1.0); // GOLD
+pub const CHECKPOINT_REACHED_COLOR: Color = Color::new(1.0, 0.5, 0.0, 1.0); // ORANGE
+pub const EDITOR_SELECTION_COLOR: Color = Color::new(1.0, 0.0, 1.0, 1.0); // MAGENTA
+pub const EDITOR_OUTLINE_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.3); // Faint WHITE
@@ -0,0 +1,399 @@
+//! Editor Module
+//!
+//! This module holds the level being edited in the level editor and the edits made to it:
+//! placing, moving, resizing and deleting what the level contains, with undo and redo. The
+//! editor screen in `game_states::editor` turns mouse and keyboard input into these edits.
+
+use std::mem;
+use std::path::PathBuf;
+
+use crate::constants::{
+    BADDIE_SIZE, BLOCK_SIZE, CHECKPOINT_HEIGHT, CHECKPOINT_WIDTH, DOOR_HEIGHT, DOOR_WIDTH,
+    EDITOR_HISTORY_LIMIT, EDITOR_SNAP, ITEM_SIZE, KEY_SIZE, PLAYER_SIZE,
+};
+use crate::level_file::{Area, LevelFile, PlatformSpec};
+use crate::physics::Solidity;
+use macroquad::prelude::*;
+
+/// Something of the level that can be selected in the editor. Indices refer to the level's
+/// lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity {
+    Platform(usize),
+    Slope(usize),
+    Checkpoint(usize),
+    Key(usize),
+    Block(usize),
+    Item(usize),
+    Baddie(usize),
+    Player,
+    Exit,
+}
+
+/// What a click in the level does: select what is under the mouse, draw a platform or drop
+/// something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Select,
+    Platform,
+    Block,
+    Item,
+    Key,
+    Baddie,
+    Player,
+}
+
+impl Tool {
+    /// Every tool, in the order of the number keys that pick them.
+    pub const ALL: [Tool; 7] = [
+        Tool::Select,
+        Tool::Platform,
+        Tool::Block,
+        Tool::Item,
+        Tool::Key,
+        Tool::Baddie,
+        Tool::Player,
+    ];
+
+    /// Returns the name of the tool, as shown in the editor.
+    pub fn label(self) -> &'static str {
+        match self {
+            Tool::Select => "Select",
+            Tool::Platform => "Platform",
+            Tool::Block => "Block",
+            Tool::Item => "Item",
+            Tool::Key => "Key",
+            Tool::Baddie => "Baddie",
+            Tool::Player => "Player",
+        }
+    }
+}
+
+/// Rounds a point to the editor's grid.
+pub fn snap(point: Vec2) -> Vec2 {
+    (point / EDITOR_SNAP).round() * EDITOR_SNAP
+}
+
+/// A level being edited, saved to `path`.
+pub struct Editor {
+    pub level: LevelFile,
+    pub path: PathBuf,
+    pub selected: Option<Entity>,
+    /// The level before each edit, the latest last.
+    undo: Vec<LevelFile>,
+    /// The edits undone, the latest last, until a new edit is made.
+    redo: Vec<LevelFile>,
+    /// The level as it was last saved or opened.
+    saved: LevelFile,
+}
+
+impl Editor {
+    /// Opens `level` for editing, to be saved to `path`.
+    pub fn new(level: LevelFile, path: PathBuf) -> Self {
+        Self {
+            saved: level.clone(),
+            level,
+            path,
+            selected: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Returns every entity of the level, from the bottom to the top of the drawing order.
+    pub fn entities(&self) -> Vec<Entity> {
+        let level = &self.level;
+        let mut entities = Vec::new();
+        entities.extend((0..level.platforms.len()).map(Entity::Platform));
+        entities.extend((0..level.slopes.len()).map(Entity::Slope));
+        entities.extend((0..level.checkpoints.len()).map(Entity::Checkpoint));
+        entities.push(Entity::Exit);
+        entities.extend((0..level.keys.len()).map(Entity::Key));
+        entities.extend((0..level.blocks.len()).map(Entity::Block));
+        entities.extend((0..level.items.len()).map(Entity::Item));
+        entities.extend((0..level.baddies.len()).map(Entity::Baddie));
+        entities.push(Entity::Player);
+        entities
+    }
+
+    /// Returns the topmost entity at `point`, if any.
+    pub fn entity_at(&self, point: Vec2) -> Option<Entity> {
+        self.entities()
+            .into_iter()
+            .rev()
+            .find(|&entity| self.rect(entity).contains(point))
+    }
+
+    /// Returns the area an entity covers in the level.
+    pub fn rect(&self, entity: Entity) -> Rect {
+        let level = &self.level;
+        let spawn = |(x, y): (f32, f32), size: f32| Rect::new(x, y, size, size);
+        // The exit and checkpoints are placed by the middle of their bottom edge.
+        let standing = |(x, y): (f32, f32), w: f32, h: f32| Rect::new(x - w / 2., y - h, w, h);
+        match entity {
+            Entity::Platform(i) => level.platforms[i].rect.0,
+            Entity::Slope(i) => level.slopes[i].rect.0,
+            Entity::Checkpoint(i) => {
+                standing(level.checkpoints[i], CHECKPOINT_WIDTH, CHECKPOINT_HEIGHT)
+            }
+            Entity::Key(i) => spawn(level.keys[i], KEY_SIZE),
+            Entity::Block(i) => spawn(level.blocks[i], BLOCK_SIZE),
+            Entity::Item(i) => spawn(level.items[i], ITEM_SIZE),
+            Entity::Baddie(i) => spawn(level.baddies[i], BADDIE_SIZE),
+            Entity::Player => spawn(level.player, PLAYER_SIZE),
+            Entity::Exit => standing(level.exit, DOOR_WIDTH, DOOR_HEIGHT),
+        }
+    }
+
+    /// Moves an entity so that its top-left corner is at `position`. A moving platform's
+    /// waypoints move with it. The move is not recorded: see `record`.
+    pub fn move_to(&mut self, entity: Entity, position: Vec2) {
+        let delta = position - self.rect(entity).point();
+        let shift = |(x, y): &mut (f32, f32)| {
+            *x += delta.x;
+            *y += delta.y;
+        };
+        let level = &mut self.level;
+        match entity {
+            Entity::Platform(i) => {
+                let platform = &mut level.platforms[i];
+                platform.rect.0 = platform.rect.0.offset(delta);
+                if let Some(path) = &mut platform.path {
+                    path.to.iter_mut().for_each(shift);
+                }
+            }
+            Entity::Slope(i) => level.slopes[i].rect.0 = level.slopes[i].rect.0.offset(delta),
+            Entity::Checkpoint(i) => shift(&mut level.checkpoints[i]),
+            Entity::Key(i) => shift(&mut level.keys[i]),
+            Entity::Block(i) => shift(&mut level.blocks[i]),
+            Entity::Item(i) => shift(&mut level.items[i]),
+            Entity::Baddie(i) => shift(&mut level.baddies[i]),
+            Entity::Player => shift(&mut level.player),
+            Entity::Exit => shift(&mut level.exit),
+        }
+    }
+
+    /// Resizes a platform or a slope from its top-left corner, to at least one grid step.
+    /// Returns whether the entity can be resized. The change is not recorded: see `record`.
+    pub fn resize(&mut self, entity: Entity, size: Vec2) -> bool {
+        let rect = match entity {
+            Entity::Platform(i) => &mut self.level.platforms[i].rect.0,
+            Entity::Slope(i) => &mut self.level.slopes[i].rect.0,
+            _ => return false,
+        };
+        let size = size.max(Vec2::splat(EDITOR_SNAP));
+        (rect.w, rect.h) = (size.x, size.y);
+        true
+    }
+
+    /// Records that the level was `before` until now, for the change to be undone. Edits made
+    /// over several frames, such as dragging, are recorded once they are over.
+    pub fn record(&mut self, before: LevelFile) {
+        if before == self.level {
+            return;
+        }
+        if self.undo.len() == EDITOR_HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    /// Adds a still platform covering `rect` and returns it.
+    pub fn add_platform(&mut self, rect: Rect, solidity: Solidity) -> Entity {
+        let before = self.level.clone();
+        self.level.platforms.push(PlatformSpec {
+            rect: Area(rect),
+            solidity,
+            path: None,
+            hidden: false,
+        });
+        self.record(before);
+        Entity::Platform(self.level.platforms.len() - 1)
+    }
+
+    /// Drops what `tool` places, centred on `center` and snapped to the grid, and returns it.
+    /// There is only one player spawn, which the player tool moves. Returns `None` for tools
+    /// that do not drop anything.
+    pub fn place(&mut self, tool: Tool, center: Vec2) -> Option<Entity> {
+        let before = self.level.clone();
+        let level = &mut self.level;
+        let entity = match tool {
+            Tool::Select | Tool::Platform => return None,
+            Tool::Block => {
+                level.blocks.push((0., 0.));
+                Entity::Block(level.blocks.len() - 1)
+            }
+            Tool::Item => {
+                level.items.push((0., 0.));
+                Entity::Item(level.items.len() - 1)
+            }
+            Tool::Key => {
+                level.keys.push((0., 0.));
+                Entity::Key(level.keys.len() - 1)
+            }
+            Tool::Baddie => {
+                level.baddies.push((0., 0.));
+                Entity::Baddie(level.baddies.len() - 1)
+            }
+            Tool::Player => Entity::Player,
+        };
+        let size = self.rect(entity).size();
+        self.move_to(entity, snap(center - size / 2.));
+        self.record(before);
+        Some(entity)
+    }
+
+    /// Deletes the selected entity. The player spawn and the exit cannot be deleted, as every
+    /// level has one of each. Returns whether something was deleted.
+    pub fn delete_selected(&mut self) -> bool {
+        let Some(entity) = self.selected else {
+            return false;
+        };
+        let before = self.level.clone();
+        let level = &mut self.level;
+        match entity {
+            Entity::Platform(i) => drop(level.platforms.remove(i)),
+            Entity::Slope(i) => drop(level.slopes.remove(i)),
+            Entity::Checkpoint(i) => drop(level.checkpoints.remove(i)),
+            Entity::Key(i) => drop(level.keys.remove(i)),
+            Entity::Block(i) => drop(level.blocks.remove(i)),
+            Entity::Item(i) => drop(level.items.remove(i)),
+            Entity::Baddie(i) => drop(level.baddies.remove(i)),
+            Entity::Player | Entity::Exit => return false,
+        }
+        self.selected = None;
+        self.record(before);
+        true
+    }
+
+    /// Switches the selected platform between solid and one-way. Returns whether a platform
+    /// is selected.
+    pub fn toggle_solidity(&mut self) -> bool {
+        let Some(Entity::Platform(i)) = self.selected else {
+            return false;
+        };
+        let before = self.level.clone();
+        let platform = &mut self.level.platforms[i];
+        platform.solidity = match platform.solidity {
+            Solidity::Solid => Solidity::OneWay,
+            Solidity::OneWay => Solidity::Solid,
+        };
+        self.record(before);
+        true
+    }
+
+    /// Undoes the last edit. Returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(level) => {
+                self.redo.push(mem::replace(&mut self.level, level));
+                self.selected = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the last undone edit again. Returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(level) => {
+                self.undo.push(mem::replace(&mut self.level, level));
+                self.selected = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns whether the level changed since it was last saved or opened.
+    pub fn is_modified(&self) -> bool {
+        self.level != self.saved
+    }
+
+    /// Saves the level to its level file.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.level.save(&self.path)?;
+        self.saved = self.level.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opens a small level with a platform and a key on it for editing.
+    fn editor() -> Editor {
+        let level = LevelFile::from_grid("#K   D#\n# ==  #\n#P    #\n#######").unwrap();
+        Editor::new(level, PathBuf::from("level.ron"))
+    }
+
+    #[test]
+    fn edits_can_be_undone_and_redone() {
+        let mut editor = editor();
+        let original = editor.level.clone();
+
+        let baddie = editor.place(Tool::Baddie, vec2(203., 98.)).unwrap();
+        assert_eq!(baddie, Entity::Baddie(0));
+        // Dropped centred on the mouse, snapped to the grid.
+        assert_eq!(editor.rect(baddie).point(), vec2(180., 80.));
+        let before = editor.level.clone();
+        editor.move_to(baddie, vec2(300., 60.));
+        editor.record(before);
+        editor.selected = Some(Entity::Key(0));
+        assert!(editor.delete_selected());
+        assert!(editor.level.keys.is_empty());
+        editor.selected = Some(Entity::Player);
+        assert!(!editor.delete_selected());
+        assert!(editor.is_modified());
+
+        assert!(editor.undo());
+        assert_eq!(editor.level.keys.len(), 1);
+        assert!(editor.undo());
+        assert_eq!(editor.level.baddies, vec![(180., 80.)]);
+        assert!(editor.undo());
+        assert_eq!(editor.level, original);
+        assert!(!editor.undo());
+
+        assert!(editor.redo());
+        assert!(editor.redo());
+        assert_eq!(editor.level.baddies, vec![(300., 60.)]);
+        // A new edit drops what was left to redo.
+        editor.add_platform(Rect::new(0., 0., 100., 20.), Solidity::OneWay);
+        assert!(!editor.redo());
+    }
+
+    #[test]
+    fn entities_are_picked_from_the_top_and_platforms_keep_their_path() {
+        let mut editor = editor();
+        let cell = crate::constants::LEVEL_GRID_CELL_SIZE;
+
+        // The key stands in front of the wall above the one-way platform's row.
+        let key = editor.rect(Entity::Key(0));
+        assert_eq!(editor.entity_at(key.center()), Some(Entity::Key(0)));
+        let platform = editor
+            .entity_at(vec2(2.5 * cell, cell + 5.))
+            .expect("a platform");
+        assert!(matches!(platform, Entity::Platform(_)));
+        assert_eq!(editor.entity_at(vec2(3.5 * cell, 2.5 * cell)), None);
+
+        let Entity::Platform(i) = platform else {
+            unreachable!()
+        };
+        editor.level.platforms[i].path = Some(crate::level_file::PathSpec {
+            mode: crate::platforms::PathMode::PingPong,
+            to: vec![(4. * cell, cell)],
+            speed: None,
+            wait: None,
+        });
+        editor.move_to(platform, vec2(cell, 2. * cell));
+        let path = editor.level.platforms[i].path.as_ref().unwrap();
+        assert_eq!(path.to, vec![(3. * cell, 2. * cell)]);
+
+        assert!(editor.resize(platform, vec2(300., 2.)));
+        assert_eq!(editor.rect(platform).size(), vec2(300., EDITOR_SNAP));
+        assert!(!editor.resize(Entity::Player, vec2(300., 300.)));
+    }
+}
@@ -2,72 +2,415 @@
 //!
 //! This module contains the main game loop and game state management.
 
-use crate::baddies::Baddie;
 use crate::camera::Camera;
-use crate::constants::MAX_BADDIES;
+use crate::campaign::CAMPAIGN;
+use crate::constants::{
+    CONTROLS_CONFIG_PATH, EDITOR_LEVEL_PATH, MAX_PHYSICS_STEPS_PER_FRAME, PHYSICS_STEP_RATE,
+    TILESET_PATH,
+};
+use crate::game_states::controls::ControlsMenu;
+use crate::game_states::editor::EditorScreen;
+use crate::game_states::game_over::GameOverScreen;
 use crate::game_states::{self, GameState};
+use crate::gamepad::{self, Gamepad, GamepadDevice};
 use crate::gui::Gui;
-use crate::level::{LEVEL_HEIGHT, LEVEL_WIDTH, Level};
-use crate::player::Player;
-use ::rand::{Rng, rng};
+use crate::highscores::{self, HighScore, HighScores};
+use crate::input::{Action, InputFrame, InputMap};
+use crate::level_file::LevelFile;
+use crate::replay::{Replay, ReplayPlayer};
+use crate::timestep::FixedTimestep;
+use crate::world::World;
 use macroquad::prelude::*;
+use std::path::PathBuf;
+
+/// Options given on the command line.
+#[derive(Default)]
+pub struct Options {
+    /// The seed to generate every run from, instead of a random one.
+    pub seed: Option<u64>,
+    /// Where to save the replay of each run when it ends.
+    pub record_path: Option<PathBuf>,
+    /// A replay to play back instead of reading the player's input.
+    pub replay: Option<Replay>,
+    /// A level to play on its own instead of the campaign.
+    pub level: Option<LevelFile>,
+    /// The file `level` was loaded from.
+    pub level_path: Option<PathBuf>,
+}
 
 /// Represents the main game state.
 pub struct Game {
     pub gamestate: GameState,
-    pub player: Player,
-    pub level: Level,
+    pub world: World,
     pub camera: Camera,
-    pub baddies: Vec<Baddie>,
     pub gui: Gui,
+    pub timestep: FixedTimestep,
+    /// Input gathered since the last simulation step.
+    pub pending_input: InputFrame,
+    /// The key bindings for every game action.
+    pub input_map: InputMap,
+    /// The gamepad feeding the same actions as the keyboard.
+    pub gamepad: Gamepad<Box<dyn GamepadDevice>>,
+    /// Whether the level is paused.
+    pub paused: bool,
+    /// Whether the editor's hotkey was pressed once in the pause overlay, which pressing it
+    /// again confirms, abandoning the run.
+    pub confirm_editor: bool,
+    /// The state of the rebinding screen.
+    pub controls_menu: ControlsMenu,
+    /// The command-line options, applied to every new run.
+    pub options: Options,
+    /// The replay being recorded for the current run, when `--record` was given.
+    pub recording: Option<Replay>,
+    /// The replay being played back for the current run, when `--replay` was given.
+    pub playback: Option<ReplayPlayer>,
+    /// The points of the levels completed so far in the current run.
+    pub score: u32,
+    /// The seed the current run started from.
+    pub seed: u64,
+    /// The time spent in the levels completed so far in the current run, in seconds.
+    pub time: f32,
+    /// The best runs, loaded from and saved to `high_scores_path`.
+    pub high_scores: HighScores,
+    /// Where the high scores are saved, or `None` if there is no user data directory.
+    pub high_scores_path: Option<PathBuf>,
+    /// Whether the title screen shows the high-score table.
+    pub show_high_scores: bool,
+    /// The state of the game-over screen.
+    pub game_over_screen: GameOverScreen,
+    /// The texture the tiles of levels imported from Tiled are drawn from, if it could be loaded.
+    pub tileset: Option<Texture2D>,
+    /// The level editor, while it is open.
+    pub editor: Option<EditorScreen>,
 }
 
 impl Game {
-    /// Creates a new game instance.
-    pub async fn new() -> Self {
-        let player = Player::new();
-        let level = Level::new().await;
-        let camera = Camera::new();
+    /// Creates a new game instance and prepares its first run according to `options`.
+    pub async fn new(options: Options) -> Self {
+        let seed = Self::run_seed(&options);
+        let world = Self::level_world(&options, seed, 0);
+        let camera = Camera::new(world.level.size);
         let gui = Gui::new();
-        let mut baddies = Vec::new();
-
-        for _ in 0..MAX_BADDIES {
-            let x = rng().random_range(0.0..LEVEL_WIDTH);
-            let y = LEVEL_HEIGHT / 2.0;
-            baddies.push(Baddie::new(vec2(x, y)));
-        }
+        let timestep = Self::run_timestep(&options);
+        let input_map = InputMap::load(CONTROLS_CONFIG_PATH).unwrap_or_else(|err| {
+            eprintln!(
+                "Invalid {} ({}), using default controls",
+                CONTROLS_CONFIG_PATH, err
+            );
+            InputMap::default()
+        });
+        let high_scores_path = highscores::default_path();
+        let high_scores = high_scores_path
+            .as_ref()
+            .map(|path| {
+                HighScores::load(path).unwrap_or_else(|err| {
+                    eprintln!("Invalid {} ({}), starting a new table", path.display(), err);
+                    HighScores::default()
+                })
+            })
+            .unwrap_or_default();
+        let tileset = match load_texture(TILESET_PATH).await {
+            Ok(texture) => {
+                texture.set_filter(FilterMode::Nearest);
+                Some(texture)
+            }
+            Err(err) => {
+                eprintln!(
+                    "Cannot load {} ({}), drawing plain tiles",
+                    TILESET_PATH, err
+                );
+                None
+            }
+        };
 
         Self {
             gamestate: GameState::Intro,
-            player,
-            level,
+            world,
             camera,
-            baddies,
             gui,
+            timestep,
+            pending_input: InputFrame::default(),
+            input_map,
+            gamepad: Gamepad::new(gamepad::system_device()),
+            paused: false,
+            confirm_editor: false,
+            controls_menu: ControlsMenu::new(),
+            recording: Self::run_recording(&options, seed),
+            playback: options.replay.clone().map(ReplayPlayer::new),
+            options,
+            score: 0,
+            seed,
+            time: 0.0,
+            high_scores,
+            high_scores_path,
+            show_high_scores: false,
+            game_over_screen: GameOverScreen::new(),
+            tileset,
+            editor: None,
+        }
+    }
+
+    /// Starts a new run with a new world, keeping the controls and devices.
+    pub fn restart(&mut self) {
+        let seed = Self::run_seed(&self.options);
+        self.world = Self::level_world(&self.options, seed, 0);
+        self.camera = Camera::new(self.world.level.size);
+        self.timestep = Self::run_timestep(&self.options);
+        self.pending_input = InputFrame::default();
+        self.paused = false;
+        self.recording = Self::run_recording(&self.options, seed);
+        self.playback = self.options.replay.clone().map(ReplayPlayer::new);
+        self.score = 0;
+        self.seed = seed;
+        self.time = 0.0;
+    }
+
+    /// Adds the tally of the level just completed to the run's score and returns the state
+    /// that follows: the next level of the campaign or, after the last one, the game-over
+    /// screen of a won run.
+    pub fn complete_level(&mut self) -> GameState {
+        self.score += self.world.tally().total();
+        self.time += self.world.elapsed;
+        let next = self.world.level_index + 1;
+        if self.options.level.is_none() && next < CAMPAIGN.len() {
+            GameState::Playing { level_index: next }
+        } else {
+            self.end_run(true);
+            GameState::GameOver
+        }
+    }
+
+    /// Starts the level at `level_index` in the campaign, with baddies driven by the run's seed
+    /// plus the level's index. The player keeps their lives. Only the first level of a run is
+    /// recorded to, or played back from, a replay.
+    pub fn start_level(&mut self, level_index: usize) {
+        let lives = self.world.player.lives;
+        self.world = Self::level_world(&self.options, self.seed, level_index);
+        self.world.player.lives = lives;
+        self.camera = Camera::new(self.world.level.size);
+        self.timestep = Self::run_timestep(&self.options);
+        self.pending_input = InputFrame::default();
+        self.paused = false;
+        self.recording = None;
+        self.playback = None;
+    }
+
+    /// Opens the level editor on the level being played: the level given on the command line,
+    /// saved back next to its file, or the campaign's, saved to `EDITOR_LEVEL_PATH`.
+    pub fn open_editor(&mut self) {
+        let level = match &self.options.level {
+            Some(level) => level.clone(),
+            None => CAMPAIGN[self.world.level_index].level_file(),
+        };
+        let path = match &self.options.level_path {
+            Some(path) => path.with_extension("ron"),
+            None => PathBuf::from(EDITOR_LEVEL_PATH),
+        };
+        self.editor = Some(EditorScreen::new(level, path));
+        self.confirm_editor = false;
+        self.gamestate = GameState::Editor;
+    }
+
+    /// Closes the level editor and goes back to the title screen, with a new run.
+    pub fn close_editor(&mut self) {
+        self.editor = None;
+        self.restart();
+        self.gamestate = GameState::Intro;
+    }
+
+    /// Plays the level being edited from its start, until the player dies or walks out. The
+    /// play-test is neither recorded nor scored.
+    pub fn start_play_test(&mut self) {
+        let Some(screen) = &mut self.editor else {
+            return;
+        };
+        screen.playtesting = true;
+        self.world = World::from_file(&screen.editor.level, self.seed, 0);
+        self.camera = Camera::new(self.world.level.size);
+        self.timestep = FixedTimestep::new(PHYSICS_STEP_RATE, MAX_PHYSICS_STEPS_PER_FRAME);
+        self.pending_input = InputFrame::default();
+        self.paused = false;
+        self.recording = None;
+        self.playback = None;
+        self.gamestate = GameState::Playing { level_index: 0 };
+    }
+
+    /// Returns whether the level being played is a play-test of the level editor.
+    pub fn is_play_testing(&self) -> bool {
+        self.editor
+            .as_ref()
+            .is_some_and(|screen| screen.playtesting)
+    }
+
+    /// Ends the play-test and goes back to the level editor.
+    pub fn end_play_test(&mut self) {
+        if let Some(screen) = &mut self.editor {
+            screen.playtesting = false;
+        }
+        self.gamestate = GameState::Editor;
+    }
+
+    /// Returns the name of the level being played, or just "Level" for a level given on the
+    /// command line.
+    pub fn level_name(&self) -> &'static str {
+        match self.options.level {
+            Some(_) => "Level",
+            None => CAMPAIGN[self.world.level_index].name,
+        }
+    }
+
+    /// Returns the run's score: the levels completed so far, and the points earned in the
+    /// current one until it is complete, when they are added to the run's score.
+    pub fn run_score(&self) -> u32 {
+        if self.world.is_complete() {
+            self.score
+        } else {
+            self.score + self.world.score.points()
         }
     }
 
+    /// Returns how long the run has lasted, in seconds, counted the same way as the score.
+    pub fn run_time(&self) -> f32 {
+        if self.world.is_complete() {
+            self.time
+        } else {
+            self.time + self.world.elapsed
+        }
+    }
+
+    /// Ends the current run, which was `won` or lost: saves its replay and prepares the
+    /// game-over screen, which asks for a name if the run made it into the high-score table.
+    pub fn end_run(&mut self, won: bool) {
+        self.save_recording();
+        let qualifies = self.high_scores.qualifies(self.run_score());
+        self.game_over_screen.open(won, qualifies);
+    }
+
+    /// Enters the run that just ended in the high-score table under `name`, saves the table
+    /// and returns the run's rank in it.
+    pub fn enter_high_score(&mut self, name: String) -> Option<usize> {
+        let rank = self.high_scores.insert(HighScore {
+            name,
+            score: self.run_score(),
+            level: self.world.level_index as u32 + 1,
+            time: self.run_time(),
+            seed: self.seed,
+        });
+        if let Some(path) = &self.high_scores_path
+            && let Err(err) = self.high_scores.save(path)
+        {
+            eprintln!("Could not save {}: {}", path.display(), err);
+        }
+        rank
+    }
+
+    /// Picks the seed of a new run: the replayed run's seed, the requested seed or a random
+    /// one. The seed is printed so that the run can be reproduced.
+    fn run_seed(options: &Options) -> u64 {
+        let seed = match &options.replay {
+            Some(replay) => replay.seed,
+            None => options.seed.unwrap_or_else(::rand::random),
+        };
+        println!("Seed: {}", seed);
+        seed
+    }
+
+    /// Creates the world of the level at `level_index` in a run started from `seed`: the level
+    /// given on the command line, or the campaign's. Baddies are driven by the seed plus the
+    /// level's index.
+    fn level_world(options: &Options, seed: u64, level_index: usize) -> World {
+        let seed = seed.wrapping_add(level_index as u64);
+        match &options.level {
+            Some(level) => World::from_file(level, seed, level_index),
+            None => World::for_level(seed, level_index),
+        }
+    }
+
+    /// Creates the timestep of a new run, at the replay's step rate when playing one back.
+    fn run_timestep(options: &Options) -> FixedTimestep {
+        let rate = options
+            .replay
+            .as_ref()
+            .map_or(PHYSICS_STEP_RATE, |replay| replay.step_rate);
+        FixedTimestep::new(rate, MAX_PHYSICS_STEPS_PER_FRAME)
+    }
+
+    /// Creates the replay recording a new run, when recording was requested. Replays only
+    /// record runs of the campaign.
+    fn run_recording(options: &Options, seed: u64) -> Option<Replay> {
+        options
+            .record_path
+            .as_ref()
+            .filter(|_| options.level.is_none())
+            .map(|_| Replay::new(seed, PHYSICS_STEP_RATE))
+    }
+
+    /// Saves the replay of the run that just ended, when recording.
+    pub fn save_recording(&mut self) {
+        if let (Some(replay), Some(path)) = (self.recording.take(), &self.options.record_path) {
+            match replay.save(path) {
+                Ok(()) => println!("Replay saved to {}", path.display()),
+                Err(err) => eprintln!("Could not save replay to {}: {}", path.display(), err),
+            }
+        }
+    }
+
+    /// Returns whether an action is held on the keyboard or the gamepad.
+    pub fn action_down(&self, action: Action) -> bool {
+        self.input_map.is_down(action) || self.gamepad.is_down(action)
+    }
+
+    /// Returns whether an action was triggered on the keyboard or the gamepad during this frame.
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.input_map.is_pressed(action) || self.gamepad.is_pressed(action)
+    }
+
     /// Runs the main game loop.
     async fn run(&mut self) {
         loop {
+            self.gamepad.update();
+            // The editor's hotkey opens it from the title screen and closes it. A run in progress
+            // is only left for the editor from the pause overlay.
+            if is_key_pressed(KeyCode::F2) {
+                match self.gamestate {
+                    GameState::Intro => self.open_editor(),
+                    GameState::Editor => {
+                        if let Some(screen) = &mut self.editor
+                            && screen.can_leave()
+                        {
+                            self.close_editor();
+                        }
+                    }
+                    _ => {}
+                }
+            }
             match self.gamestate {
                 GameState::Intro => {
                     game_states::intro::run_intro(self).await;
                 }
-                GameState::Level1 => {
-                    game_states::level1::run_level1(self).await;
+                GameState::Playing { level_index } => {
+                    game_states::playing::run_playing(self, level_index).await;
+                }
+                GameState::LevelComplete => {
+                    game_states::level_complete::run_level_complete(self).await;
                 }
                 GameState::GameOver => {
                     game_states::game_over::run_game_over(self).await;
                 }
+                GameState::Controls => {
+                    game_states::controls::run_controls(self).await;
+                }
+                GameState::Editor => {
+                    game_states::editor::run_editor(self).await;
+                }
             }
             next_frame().await
         }
     }
 }
 
-/// Runs the main game loop.
-pub async fn run() {
-    let mut game = Game::new().await;
+/// Runs the main game loop with the given command-line options.
+pub async fn run(options: Options) {
+    let mut game = Game::new(options).await;
     game.run().await;
 }
@@ -0,0 +1,102 @@
+use macroquad::prelude::*;
+
+use crate::constants::CONTROLS_CONFIG_PATH;
+use crate::game::Game;
+use crate::game_states::GameState;
+use crate::input::Action;
+
+/// The state of the rebinding screen.
+///
+/// The screen itself is navigated with fixed keys (UP/DOWN, ENTER, BACKSPACE, ESCAPE), so that
+/// it remains usable whatever the bindings are.
+pub struct ControlsMenu {
+    /// The index of the selected action in `Action::ALL`.
+    pub selected: usize,
+    /// Whether the next key pressed is added to the selected action.
+    pub listening: bool,
+    /// The state to go back to when leaving the screen.
+    pub return_state: GameState,
+}
+
+impl ControlsMenu {
+    /// Creates a new rebinding screen state with the first action selected.
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            listening: false,
+            return_state: GameState::Intro,
+        }
+    }
+
+    /// Prepares the screen to be shown, returning to `return_state` when it is left.
+    pub fn open(&mut self, return_state: GameState) {
+        self.selected = 0;
+        self.listening = false;
+        self.return_state = return_state;
+    }
+}
+
+pub async fn run_controls(game: &mut Game) {
+    update(game);
+    draw(game);
+}
+
+/// Handles navigation and rebinding on the controls screen.
+fn update(game: &mut Game) {
+    let menu = &mut game.controls_menu;
+    let action = Action::ALL[menu.selected];
+
+    if menu.listening {
+        match get_last_key_pressed() {
+            Some(KeyCode::Escape) => menu.listening = false,
+            Some(key) => {
+                game.input_map.bind(action, key);
+                menu.listening = false;
+            }
+            None => {}
+        }
+        return;
+    }
+
+    if is_key_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + Action::ALL.len() - 1) % Action::ALL.len();
+    } else if is_key_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % Action::ALL.len();
+    } else if is_key_pressed(KeyCode::Enter) {
+        menu.listening = true;
+    } else if is_key_pressed(KeyCode::Backspace) {
+        game.input_map.clear(action);
+    } else if is_key_pressed(KeyCode::Escape) {
+        if let Err(err) = game.input_map.save(CONTROLS_CONFIG_PATH) {
+            eprintln!("Could not save {}: {}", CONTROLS_CONFIG_PATH, err);
+        }
+        game.gamestate = menu.return_state;
+    }
+}
+
+/// Draws the list of actions with their bindings.
+fn draw(game: &Game) {
+    let menu = &game.controls_menu;
+    clear_background(BLACK);
+    draw_text("CONTROLS", screen_width() / 2. - 120., 80., 50., WHITE);
+
+    for (i, action) in Action::ALL.into_iter().enumerate() {
+        let y = 160. + i as f32 * 40.;
+        let color = if i == menu.selected { YELLOW } else { WHITE };
+        let keys = if i == menu.selected && menu.listening {
+            "press a key...".to_owned()
+        } else {
+            game.input_map.describe(action)
+        };
+        draw_text(action.label(), 100., y, 30., color);
+        draw_text(&keys, 400., y, 30., color);
+    }
+
+    draw_text(
+        "UP/DOWN: select   ENTER: add key   BACKSPACE: clear   ESCAPE: save and return",
+        100.,
+        screen_height() - 60.,
+        20.,
+        GRAY,
+    );
+}
@@ -0,0 +1,389 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+use crate::camera::Camera;
+use crate::constants::{
+    BACKGROUND_COLOR, EDITOR_HANDLE_SIZE, EDITOR_OUTLINE_COLOR, EDITOR_PAN_SPEED,
+    EDITOR_SELECTION_COLOR, PLATFORM_COLOR,
+};
+use crate::editor::{self, Editor, Entity, Tool};
+use crate::game::Game;
+use crate::level_file::LevelFile;
+use crate::physics::Solidity;
+use crate::world::World;
+
+/// The keys that pick the tools, in the order of `Tool::ALL`.
+const TOOL_KEYS: [KeyCode; 7] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+];
+
+/// What the mouse is doing in the editor, from the press of a button to its release.
+enum Drag {
+    /// Moving `entity`, held `offset` from its top-left corner. `before` is the level as it was
+    /// when the drag started.
+    Move {
+        entity: Entity,
+        offset: Vec2,
+        before: LevelFile,
+    },
+    /// Resizing `entity` by its bottom-right corner.
+    Resize { entity: Entity, before: LevelFile },
+    /// Drawing a new platform from the corner at `start`.
+    Draw { start: Vec2 },
+    /// Panning the camera with the right button, last seen at `last` on the screen.
+    Pan { last: Vec2 },
+}
+
+/// The state of the level editor.
+///
+/// The editor is driven with the mouse and fixed keys, so that it remains usable whatever the
+/// bindings are.
+pub struct EditorScreen {
+    pub editor: Editor,
+    camera: Camera,
+    tool: Tool,
+    drag: Option<Drag>,
+    /// The level being edited as a world, to draw it the way it is played.
+    preview: World,
+    /// The level `preview` was built from.
+    previewed: LevelFile,
+    /// Whether the level is being play-tested, to come back to the editor when it ends.
+    pub playtesting: bool,
+    /// Whether leaving was asked for once with unsaved changes, which leaving again discards.
+    leaving: bool,
+    /// The outcome of the last command, such as saving.
+    message: String,
+}
+
+impl EditorScreen {
+    /// Opens the editor on `level`, to be saved to `path`.
+    pub fn new(level: LevelFile, path: PathBuf) -> Self {
+        Self {
+            camera: Camera::new(vec2(level.size.0, level.size.1)),
+            tool: Tool::Select,
+            drag: None,
+            preview: World::from_file(&level, 0, 0),
+            previewed: level.clone(),
+            playtesting: false,
+            leaving: false,
+            message: String::new(),
+            editor: Editor::new(level, path),
+        }
+    }
+
+    /// Returns whether the editor can be left: once there are no unsaved changes, or when
+    /// leaving is asked for a second time.
+    pub fn can_leave(&mut self) -> bool {
+        if self.leaving || !self.editor.is_modified() {
+            return true;
+        }
+        self.leaving = true;
+        self.message = "Unsaved changes: leave again to discard them".to_owned();
+        false
+    }
+
+    /// Handles the keyboard and mouse for the current frame.
+    fn update(&mut self, dt: f32) {
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        if ctrl {
+            if is_key_pressed(KeyCode::Z) && !shift {
+                self.message = if self.editor.undo() {
+                    ""
+                } else {
+                    "Nothing to undo"
+                }
+                .to_owned();
+            } else if is_key_pressed(KeyCode::Y) || (is_key_pressed(KeyCode::Z) && shift) {
+                self.message = if self.editor.redo() {
+                    ""
+                } else {
+                    "Nothing to redo"
+                }
+                .to_owned();
+            } else if is_key_pressed(KeyCode::S) {
+                self.save();
+            }
+        } else {
+            self.update_keys(dt);
+        }
+        self.update_mouse();
+
+        if self.editor.level != self.previewed {
+            self.preview = World::from_file(&self.editor.level, 0, 0);
+            self.previewed = self.editor.level.clone();
+        }
+    }
+
+    /// Pans the camera with the arrow keys, and handles the keys that pick tools and change
+    /// the selection.
+    fn update_keys(&mut self, dt: f32) {
+        let mut direction = Vec2::ZERO;
+        if is_key_down(KeyCode::Left) {
+            direction.x -= 1.;
+        }
+        if is_key_down(KeyCode::Right) {
+            direction.x += 1.;
+        }
+        if is_key_down(KeyCode::Up) {
+            direction.y -= 1.;
+        }
+        if is_key_down(KeyCode::Down) {
+            direction.y += 1.;
+        }
+        self.camera.pan(direction * EDITOR_PAN_SPEED * dt);
+
+        for (key, tool) in TOOL_KEYS.into_iter().zip(Tool::ALL) {
+            if is_key_pressed(key) {
+                self.tool = tool;
+            }
+        }
+        if (is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace))
+            && self.editor.selected.is_some()
+            && !self.editor.delete_selected()
+        {
+            self.message = "The player spawn and the exit cannot be deleted".to_owned();
+        }
+        if is_key_pressed(KeyCode::T) && !self.editor.toggle_solidity() {
+            self.message = "Select a platform to switch it between solid and one-way".to_owned();
+        }
+    }
+
+    /// Selects, moves, resizes, draws and drops entities with the left button, and pans the
+    /// camera with the right one.
+    fn update_mouse(&mut self) {
+        let screen_mouse = Vec2::from(mouse_position());
+        let camera = self.camera.camera_2d();
+        let mouse = camera.screen_to_world(screen_mouse);
+
+        if self.drag.is_none() {
+            if is_mouse_button_pressed(MouseButton::Right) {
+                self.drag = Some(Drag::Pan { last: screen_mouse });
+            } else if is_mouse_button_pressed(MouseButton::Left) {
+                self.leaving = false;
+                self.drag = match self.tool {
+                    Tool::Select => self.grab(mouse),
+                    Tool::Platform => Some(Drag::Draw {
+                        start: editor::snap(mouse),
+                    }),
+                    tool => {
+                        self.editor.selected = self.editor.place(tool, mouse);
+                        None
+                    }
+                };
+            }
+        }
+
+        match &mut self.drag {
+            Some(Drag::Move { entity, offset, .. }) => {
+                self.editor.move_to(*entity, editor::snap(mouse - *offset));
+            }
+            Some(Drag::Resize { entity, .. }) => {
+                let corner = self.editor.rect(*entity).point();
+                self.editor.resize(*entity, editor::snap(mouse) - corner);
+            }
+            Some(Drag::Pan { last }) => {
+                self.camera.pan(camera.screen_to_world(*last) - mouse);
+                *last = screen_mouse;
+            }
+            Some(Drag::Draw { .. }) | None => {}
+        }
+
+        let released = match self.drag {
+            Some(Drag::Pan { .. }) => is_mouse_button_released(MouseButton::Right),
+            Some(_) => is_mouse_button_released(MouseButton::Left),
+            None => false,
+        };
+        if released {
+            match self.drag.take() {
+                Some(Drag::Move { before, .. } | Drag::Resize { before, .. }) => {
+                    self.editor.record(before);
+                }
+                Some(Drag::Draw { start }) => {
+                    let rect = drawn_rect(start, editor::snap(mouse));
+                    if rect.w > 0. && rect.h > 0. {
+                        let platform = self.editor.add_platform(rect, Solidity::Solid);
+                        self.editor.selected = Some(platform);
+                    }
+                }
+                Some(Drag::Pan { .. }) | None => {}
+            }
+        }
+    }
+
+    /// Starts resizing the selected platform or slope if `mouse` is on its handle, or selects
+    /// the entity under `mouse` and starts moving it.
+    fn grab(&mut self, mouse: Vec2) -> Option<Drag> {
+        let before = self.editor.level.clone();
+        if let Some(entity) = self.editor.selected
+            && matches!(entity, Entity::Platform(_) | Entity::Slope(_))
+            && handle(self.editor.rect(entity)).contains(mouse)
+        {
+            return Some(Drag::Resize { entity, before });
+        }
+        self.editor.selected = self.editor.entity_at(mouse);
+        self.editor.selected.map(|entity| Drag::Move {
+            entity,
+            offset: mouse - self.editor.rect(entity).point(),
+            before,
+        })
+    }
+
+    /// Saves the level, reporting the outcome.
+    fn save(&mut self) {
+        let path = self.editor.path.display().to_string();
+        self.message = match self.editor.save() {
+            Ok(()) => {
+                self.leaving = false;
+                println!("Level saved to {}", path);
+                format!("Saved to {}", path)
+            }
+            Err(err) => {
+                eprintln!("Could not save {}: {}", path, err);
+                format!("Could not save {}: {}", path, err)
+            }
+        };
+    }
+}
+
+/// Returns the rectangle between two opposite corners.
+fn drawn_rect(a: Vec2, b: Vec2) -> Rect {
+    let min = a.min(b);
+    let size = (a - b).abs();
+    Rect::new(min.x, min.y, size.x, size.y)
+}
+
+/// Returns the handle that resizes `rect`, on its bottom-right corner.
+fn handle(rect: Rect) -> Rect {
+    Rect::new(
+        rect.right() - EDITOR_HANDLE_SIZE / 2.,
+        rect.bottom() - EDITOR_HANDLE_SIZE / 2.,
+        EDITOR_HANDLE_SIZE,
+        EDITOR_HANDLE_SIZE,
+    )
+}
+
+pub async fn run_editor(game: &mut Game) {
+    update(game);
+    draw(game);
+}
+
+/// Handles the keys that leave the editor or play-test the level, then the editing itself.
+fn update(game: &mut Game) {
+    let Some(screen) = &mut game.editor else {
+        return;
+    };
+    if is_key_pressed(KeyCode::F5) {
+        game.start_play_test();
+    } else if is_key_pressed(KeyCode::Escape) {
+        if screen.can_leave() {
+            game.close_editor();
+        }
+    } else {
+        screen.update(get_frame_time());
+    }
+}
+
+/// Draws the level being edited, the outline of every entity and the selection, then the
+/// tools and the editor's keys.
+fn draw(game: &Game) {
+    let Some(screen) = &game.editor else {
+        return;
+    };
+    let editor = &screen.editor;
+    clear_background(BACKGROUND_COLOR);
+
+    let camera = screen.camera.camera_2d();
+    set_camera(&camera);
+    let world = &screen.preview;
+    world.level.draw(1., game.tileset.as_ref());
+    world.player.draw(1.);
+    for baddie in world.baddies.values() {
+        baddie.draw(1.);
+    }
+
+    // Outline every entity, so that platforms hidden under tiles can be picked too.
+    for entity in editor.entities() {
+        let rect = editor.rect(entity);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2., EDITOR_OUTLINE_COLOR);
+    }
+    if let Some(entity) = editor.selected {
+        let rect = editor.rect(entity);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 4., EDITOR_SELECTION_COLOR);
+        if let Entity::Platform(i) = entity
+            && let Some(path) = &editor.level.platforms[i].path
+        {
+            // The path of a moving platform, followed by its top-left corner.
+            let mut from = rect.point();
+            for &(x, y) in &path.to {
+                draw_line(from.x, from.y, x, y, 2., EDITOR_SELECTION_COLOR);
+                from = vec2(x, y);
+            }
+        }
+        if matches!(entity, Entity::Platform(_) | Entity::Slope(_)) {
+            let handle = handle(rect);
+            draw_rectangle(
+                handle.x,
+                handle.y,
+                handle.w,
+                handle.h,
+                EDITOR_SELECTION_COLOR,
+            );
+        }
+    }
+    if let Some(Drag::Draw { start }) = screen.drag {
+        let mouse = camera.screen_to_world(Vec2::from(mouse_position()));
+        let rect = drawn_rect(start, editor::snap(mouse));
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2., PLATFORM_COLOR);
+    }
+
+    set_default_camera();
+    draw_hud(screen);
+}
+
+/// Draws the tools, the file being edited and the editor's keys.
+fn draw_hud(screen: &EditorScreen) {
+    let mut x = 20.;
+    for (i, tool) in Tool::ALL.into_iter().enumerate() {
+        let label = format!("{} {}", i + 1, tool.label());
+        let color = if tool == screen.tool { YELLOW } else { WHITE };
+        draw_text(&label, x, 30., 24., color);
+        x += measure_text(&label, None, 24, 1.).width + 24.;
+    }
+
+    let modified = if screen.editor.is_modified() {
+        " *"
+    } else {
+        ""
+    };
+    draw_text(
+        &format!("{}{}", screen.editor.path.display(), modified),
+        20.,
+        60.,
+        20.,
+        GRAY,
+    );
+    draw_text(&screen.message, 20., 85., 20., YELLOW);
+    draw_text(
+        "LEFT: select, move, resize, draw or drop   RIGHT/ARROWS: pan   DELETE: delete   \
+         T: solid/one-way",
+        20.,
+        screen_height() - 45.,
+        20.,
+        GRAY,
+    );
+    draw_text(
+        "CTRL+Z/CTRL+Y: undo/redo   CTRL+S: save   F5: play-test   ESCAPE: leave",
+        20.,
+        screen_height() - 20.,
+        20.,
+        GRAY,
+    );
+}
@@ -1,27 +1,122 @@
 use macroquad::prelude::*;
 
+use crate::constants::HIGH_SCORE_NAME_MAX_LEN;
 use crate::game::Game;
 use crate::game_states::GameState;
+use crate::input::Action;
+
+/// The state of the game-over screen.
+///
+/// The name is typed with the keyboard, and erased with BACKSPACE whatever the bindings are.
+/// It is entered with the confirm action, so that a gamepad can enter a run without a name.
+pub struct GameOverScreen {
+    /// The name being typed, while a run that made it into the high-score table is entered.
+    pub name: Option<String>,
+    /// The rank of the run just entered, highlighted in the high-score table.
+    pub rank: Option<usize>,
+    /// Whether the run ended with the last level of the campaign complete.
+    pub won: bool,
+}
+
+impl GameOverScreen {
+    /// Creates a new game-over screen state.
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            rank: None,
+            won: false,
+        }
+    }
+
+    /// Prepares the screen to be shown for a run that was `won` or lost, asking for a name if
+    /// the run made it into the high-score table.
+    pub fn open(&mut self, won: bool, qualifies: bool) {
+        self.name = qualifies.then(String::new);
+        self.rank = None;
+        self.won = won;
+    }
+}
 
 pub async fn run_game_over(game: &mut Game) {
     clear_background(BLACK);
+    let title = if game.game_over_screen.won {
+        "YOU WIN"
+    } else {
+        "GAME OVER"
+    };
+    draw_text(title, screen_width() / 2. - 150., 80., 50., WHITE);
     draw_text(
-        "GAME OVER",
-        screen_width() / 2. - 150.,
-        screen_height() / 2. - 40.,
-        50.,
+        &format!("Score: {}", game.run_score()),
+        screen_width() / 2. - 160.,
+        130.,
+        30.,
         WHITE,
     );
     draw_text(
-        "Press ENTER to restart",
+        &format!("Seed: {}", game.seed),
         screen_width() / 2. - 160.,
-        screen_height() / 2. + 20.,
+        160.,
+        20.,
+        GRAY,
+    );
+
+    if let Some(name) = &mut game.game_over_screen.name {
+        update_name(name);
+        draw_text(
+            "NEW HIGH SCORE! Enter your name:",
+            screen_width() / 2. - 160.,
+            240.,
+            30.,
+            YELLOW,
+        );
+        draw_text(
+            &format!("{}_", name),
+            screen_width() / 2. - 160.,
+            290.,
+            30.,
+            WHITE,
+        );
+
+        if game.action_pressed(Action::Confirm) {
+            let name = game.game_over_screen.name.take().unwrap_or_default();
+            let name = if name.trim().is_empty() {
+                "???".to_owned()
+            } else {
+                name.trim().to_owned()
+            };
+            game.game_over_screen.rank = game.enter_high_score(name);
+        }
+        return;
+    }
+
+    game.high_scores.draw(220., game.game_over_screen.rank);
+    draw_text(
+        &format!(
+            "Press {} to restart",
+            game.input_map.describe(Action::Confirm)
+        ),
+        screen_width() / 2. - 160.,
+        screen_height() - 40.,
         30.,
         WHITE,
     );
 
-    if is_key_pressed(KeyCode::Enter) {
-        *game = Game::new().await;
-        game.gamestate = GameState::Level1;
+    if game.action_pressed(Action::Confirm) {
+        game.restart();
+        game.gamestate = GameState::Playing { level_index: 0 };
+    }
+}
+
+/// Adds the characters typed during this frame to `name`, and erases its last character when
+/// BACKSPACE is pressed.
+fn update_name(name: &mut String) {
+    while let Some(c) = get_char_pressed() {
+        let allowed = c.is_alphanumeric() || c == ' ' || c == '-' || c == '_';
+        if allowed && name.chars().count() < HIGH_SCORE_NAME_MAX_LEN {
+            name.push(c);
+        }
+    }
+    if is_key_pressed(KeyCode::Backspace) {
+        name.pop();
     }
-}
\ No newline at end of file
+}
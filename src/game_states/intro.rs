@@ -2,9 +2,38 @@ use macroquad::prelude::*;
 
 use crate::game::Game;
 use crate::game_states::GameState;
+use crate::input::Action;
 
 pub async fn run_intro(game: &mut Game) {
     clear_background(BLACK);
+    if game.show_high_scores {
+        game.high_scores.draw(80., None);
+        draw_text(
+            "Press H to go back",
+            screen_width() / 2. - 130.,
+            screen_height() - 40.,
+            20.,
+            GRAY,
+        );
+    } else {
+        draw_title(game);
+    }
+
+    if game.action_pressed(Action::Confirm) {
+        game.gamestate = GameState::Playing { level_index: 0 };
+    } else if is_key_pressed(KeyCode::C) {
+        game.controls_menu.open(GameState::Intro);
+        game.gamestate = GameState::Controls;
+    } else if is_key_pressed(KeyCode::H) {
+        game.show_high_scores = !game.show_high_scores;
+    } else if is_key_pressed(KeyCode::E) {
+        game.open_editor();
+    }
+}
+
+/// Draws the title and the keys to start the game, configure the controls, show the high
+/// scores or edit the level.
+fn draw_title(game: &Game) {
     draw_text(
         "PLATFORMER",
         screen_width() / 2. - 150.,
@@ -13,14 +42,37 @@ pub async fn run_intro(game: &mut Game) {
         WHITE,
     );
     draw_text(
-        "Press ENTER to start",
+        &format!(
+            "Press {} to start",
+            game.input_map.describe(Action::Confirm)
+        ),
         screen_width() / 2. - 130.,
         screen_height() / 2. + 20.,
         30.,
         WHITE,
     );
+    draw_text(
+        "Press C to configure controls, H to show high scores",
+        screen_width() / 2. - 130.,
+        screen_height() / 2. + 60.,
+        20.,
+        GRAY,
+    );
+    draw_text(
+        "Press E or F2 to edit the level",
+        screen_width() / 2. - 130.,
+        screen_height() / 2. + 85.,
+        20.,
+        GRAY,
+    );
 
-    if is_key_pressed(KeyCode::Enter) {
-        game.gamestate = GameState::Level1;
+    if game.gamepad.is_connected() {
+        draw_text(
+            "Gamepad connected",
+            screen_width() / 2. - 130.,
+            screen_height() / 2. + 115.,
+            20.,
+            GRAY,
+        );
     }
-}
\ No newline at end of file
+}
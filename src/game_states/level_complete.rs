@@ -0,0 +1,54 @@
+use macroquad::prelude::*;
+
+use crate::game::Game;
+use crate::input::Action;
+
+/// Shows how the points of the level just completed add up, until the player moves on to the
+/// next level.
+pub async fn run_level_complete(game: &mut Game) {
+    let tally = game.world.tally();
+    let lines = [
+        format!("Kills ({})", game.world.score.kills),
+        tally.kill_points.to_string(),
+        format!("Keys ({})", game.world.score.keys),
+        tally.key_points.to_string(),
+        format!("Time ({:.0}s)", game.world.elapsed),
+        tally.time_bonus.to_string(),
+        format!("Lives ({})", game.world.player.lives),
+        tally.lives_bonus.to_string(),
+        "Level total".to_owned(),
+        tally.total().to_string(),
+        "Score".to_owned(),
+        (game.score + tally.total()).to_string(),
+    ];
+
+    clear_background(BLACK);
+    let name = game.level_name().to_uppercase();
+    draw_text(
+        &format!("{} COMPLETE", name),
+        screen_width() / 2. - 200.,
+        screen_height() / 2. - 160.,
+        50.,
+        WHITE,
+    );
+    for (row, pair) in lines.chunks(2).enumerate() {
+        let y = screen_height() / 2. - 100. + row as f32 * 35.;
+        let color = if row >= 4 { YELLOW } else { WHITE };
+        draw_text(&pair[0], screen_width() / 2. - 200., y, 30., color);
+        draw_text(&pair[1], screen_width() / 2. + 100., y, 30., color);
+    }
+    draw_text(
+        &format!(
+            "Press {} to continue",
+            game.input_map.describe(Action::Confirm)
+        ),
+        screen_width() / 2. - 160.,
+        screen_height() / 2. + 140.,
+        30.,
+        WHITE,
+    );
+
+    if game.action_pressed(Action::Confirm) {
+        game.gamestate = game.complete_level();
+    }
+}
@@ -1,10 +1,20 @@
+pub mod controls;
+pub mod editor;
 pub mod game_over;
 pub mod intro;
-pub mod level1;
+pub mod level_complete;
+pub mod playing;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum GameState {
     Intro,
-    Level1,
+    /// Playing the level at `level_index` in the campaign.
+    Playing {
+        level_index: usize,
+    },
+    LevelComplete,
     GameOver,
-}
\ No newline at end of file
+    Controls,
+    /// Editing a level in the level editor.
+    Editor,
+}
@@ -0,0 +1,226 @@
+use std::time::Instant;
+
+use macroquad::prelude::*;
+
+use crate::{
+    game::Game,
+    game_states::GameState,
+    input::{Action, InputFrame},
+};
+
+use crate::constants::BACKGROUND_COLOR;
+
+const FPS_LOG_INTERVAL_FRAMES: u32 = 1000;
+
+/// Runs a frame of the level at `level_index` in the campaign, starting that level if the
+/// world holds another one.
+pub async fn run_playing(game: &mut Game, level_index: usize) {
+    if game.world.level_index != level_index {
+        game.start_level(level_index);
+    }
+
+    let mut frame_count = 0;
+    let mut last_log_time = Instant::now();
+
+    let dt = get_frame_time();
+
+    // Update
+    update(game, dt);
+
+    // Draw
+    draw(game);
+
+    // Log FPS
+    frame_count += 1;
+    log_fps(&mut frame_count, &mut last_log_time);
+}
+
+/// Updates the game state for the current frame.
+/// The frame time is accumulated and the world is advanced in fixed-length steps.
+fn update(game: &mut Game, dt: f32) {
+    // --- Play-test ---
+    // The level editor's play-test key goes back to the editor.
+    if game.is_play_testing() && is_key_pressed(KeyCode::F5) {
+        game.end_play_test();
+        return;
+    }
+
+    // --- Pause ---
+    if game.action_pressed(Action::Pause) {
+        game.paused = !game.paused;
+        game.confirm_editor = false;
+        game.pending_input = InputFrame::default();
+    }
+    if game.paused {
+        if is_key_pressed(KeyCode::C) {
+            game.controls_menu.open(game.gamestate);
+            game.gamestate = GameState::Controls;
+        } else if is_key_pressed(KeyCode::F2) && !game.is_play_testing() {
+            // Leaving the run for the editor abandons it, so it takes a second press. Its
+            // replay is still saved.
+            if game.confirm_editor {
+                game.save_recording();
+                game.open_editor();
+            } else {
+                game.confirm_editor = true;
+            }
+        }
+        return;
+    }
+
+    let input = InputFrame::from_actions(|a| game.action_down(a), |a| game.action_pressed(a));
+    game.pending_input.latch(&input);
+
+    let steps = game.timestep.advance(dt);
+    let step_duration = game.timestep.step_duration();
+    for _ in 0..steps {
+        // When playing a replay back, its recorded input replaces the player's.
+        let input = match &mut game.playback {
+            Some(playback) => match playback.next_input() {
+                Some(input) => input,
+                None => {
+                    println!("Replay finished");
+                    game.gamestate = GameState::GameOver;
+                    break;
+                }
+            },
+            None => game.pending_input,
+        };
+
+        let lives = game.world.player.lives;
+        game.world.step(&input, step_duration);
+        game.pending_input.clear_presses();
+
+        if let Some(recording) = &mut game.recording {
+            recording.record(&input, &game.world);
+        }
+        if let Some(playback) = &game.playback
+            && let Err(err) = playback.check(&game.world)
+        {
+            eprintln!("Replay {}", err);
+        }
+
+        // --- Play-test End Condition ---
+        // A play-test goes back to the editor as soon as the player dies or walks out.
+        if game.is_play_testing() && (game.world.player.lives < lives || game.world.is_complete()) {
+            game.end_play_test();
+            break;
+        }
+
+        // --- Game Over Condition ---
+        // The player lost their last life during this step.
+        if game.world.player.lives == 0 {
+            game.gamestate = GameState::GameOver;
+            break;
+        }
+
+        // --- Level Complete Condition ---
+        // The player walked into the exit once every key was collected.
+        if game.world.is_complete() {
+            game.gamestate = GameState::LevelComplete;
+            break;
+        }
+    }
+
+    if game.gamestate == GameState::GameOver {
+        game.end_run(false);
+    } else if game.gamestate == GameState::LevelComplete {
+        game.save_recording();
+    }
+
+    let alpha = game.timestep.alpha();
+    let player = &game.world.player.body;
+    let player_position = player.render_position(alpha);
+    game.camera.update(Rect::new(
+        player_position.x,
+        player_position.y,
+        player.size.x,
+        player.size.y,
+    ));
+}
+
+/// Draws the game world.
+fn draw(game: &Game) {
+    clear_background(BACKGROUND_COLOR);
+
+    set_camera(&game.camera.camera_2d());
+
+    let alpha = game.timestep.alpha();
+    let world = &game.world;
+    world.level.draw(alpha, game.tileset.as_ref());
+    world.player.draw(alpha);
+    for baddie in world.baddies.values() {
+        baddie.draw(alpha);
+    }
+
+    set_default_camera();
+
+    game.gui.draw(
+        world.keys_collected(),
+        world.level.total_keys,
+        game.run_score(),
+        world.player.lives,
+        world.player.health,
+    );
+
+    if game.paused {
+        draw_pause_overlay(game);
+    }
+}
+
+/// Draws the pause overlay on top of the frozen game world, with the keys to resume, configure
+/// the controls or leave the run for the editor.
+fn draw_pause_overlay(game: &Game) {
+    draw_rectangle(
+        0.,
+        0.,
+        screen_width(),
+        screen_height(),
+        Color::new(0., 0., 0., 0.6),
+    );
+    draw_text(
+        "PAUSED",
+        screen_width() / 2. - 90.,
+        screen_height() / 2. - 40.,
+        50.,
+        WHITE,
+    );
+    draw_text(
+        &format!(
+            "Press {} to resume, C to configure controls",
+            game.input_map.describe(Action::Pause)
+        ),
+        screen_width() / 2. - 260.,
+        screen_height() / 2. + 20.,
+        20.,
+        WHITE,
+    );
+    if game.is_play_testing() {
+        return;
+    }
+    let editor = if game.confirm_editor {
+        "Press F2 again to abandon the run and edit the level"
+    } else {
+        "Press F2 to edit the level"
+    };
+    draw_text(
+        editor,
+        screen_width() / 2. - 260.,
+        screen_height() / 2. + 50.,
+        20.,
+        if game.confirm_editor { YELLOW } else { GRAY },
+    );
+}
+
+/// Logs the average FPS to the console every `FPS_LOG_INTERVAL_FRAMES` frames.
+fn log_fps(frame_count: &mut u32, last_log_time: &mut Instant) {
+    if *frame_count >= FPS_LOG_INTERVAL_FRAMES {
+        let elapsed_time = last_log_time.elapsed().as_secs_f32();
+        let fps = *frame_count as f32 / elapsed_time;
+        println!("Average FPS over last {} frames: {:.2}", *frame_count, fps);
+
+        // Reset counter and timer
+        *frame_count = 0;
+        *last_log_time = Instant::now();
+    }
+}
@@ -0,0 +1,318 @@
+//! Gamepad Module
+//!
+//! This module feeds gamepad input into the same game actions as the keyboard. A
+//! `GamepadDevice` reports the raw state of the active pad; `Gamepad` maps that state to
+//! actions (d-pad or left stick with a deadzone for movement, face buttons for jumping and
+//! grabbing/throwing, start for pausing) and tracks hot-plugging.
+//!
+//! Real hardware is read through `gilrs` when the `gamepad` feature is enabled. In tests, the
+//! `VirtualGamepad` device is driven by code, which lets the mapping be checked without
+//! hardware.
+
+use crate::input::Action;
+use macroquad::prelude::*;
+
+/// The fraction of the stick's travel that is ignored around its center.
+pub const DEFAULT_STICK_DEADZONE: f32 = 0.3;
+
+/// The gamepad buttons the game can react to, using the position-based names of the
+/// standard layout (`South` is A on Xbox pads and Cross on PlayStation pads).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PadButton {
+    South,
+    East,
+    West,
+    Start,
+    DPadLeft,
+    DPadRight,
+    DPadDown,
+}
+
+/// A snapshot of the active gamepad's buttons and left stick.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PadState {
+    pub buttons: Vec<PadButton>,
+    /// The left stick position, each axis between -1 and 1, with y pointing down.
+    pub left_stick: Vec2,
+}
+
+impl PadState {
+    /// Returns whether a button is held down.
+    pub fn is_down(&self, button: PadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+}
+
+/// A source of gamepad state.
+pub trait GamepadDevice {
+    /// Returns the current state of the active gamepad, or `None` when no gamepad is connected.
+    /// Called once per frame.
+    fn poll(&mut self) -> Option<PadState>;
+}
+
+impl<D: GamepadDevice + ?Sized> GamepadDevice for Box<D> {
+    fn poll(&mut self) -> Option<PadState> {
+        (**self).poll()
+    }
+}
+
+/// A device that never reports a gamepad, used when gamepad support is unavailable.
+pub struct NoGamepad;
+
+impl GamepadDevice for NoGamepad {
+    fn poll(&mut self) -> Option<PadState> {
+        None
+    }
+}
+
+/// A gamepad driven by code rather than hardware.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct VirtualGamepad {
+    pub connected: bool,
+    pub state: PadState,
+}
+
+#[cfg(test)]
+impl VirtualGamepad {
+    /// Holds a button down until it is released.
+    pub fn press(&mut self, button: PadButton) {
+        if !self.state.is_down(button) {
+            self.state.buttons.push(button);
+        }
+    }
+
+    /// Releases a held button.
+    pub fn release(&mut self, button: PadButton) {
+        self.state.buttons.retain(|held| *held != button);
+    }
+}
+
+#[cfg(test)]
+impl GamepadDevice for VirtualGamepad {
+    fn poll(&mut self) -> Option<PadState> {
+        self.connected.then(|| self.state.clone())
+    }
+}
+
+/// Returns the buttons bound to an action by default.
+fn default_buttons(action: Action) -> &'static [PadButton] {
+    match action {
+        Action::MoveLeft => &[PadButton::DPadLeft],
+        Action::MoveRight => &[PadButton::DPadRight],
+        Action::MoveDown => &[PadButton::DPadDown],
+        Action::Jump => &[PadButton::South],
+        Action::GrabThrow => &[PadButton::West, PadButton::East],
+        Action::Confirm => &[PadButton::South, PadButton::Start],
+        Action::Pause => &[PadButton::Start],
+    }
+}
+
+/// Maps a gamepad device's state to game actions.
+pub struct Gamepad<D: GamepadDevice> {
+    pub device: D,
+    pub deadzone: f32,
+    current: Option<PadState>,
+    previous: Option<PadState>,
+}
+
+impl<D: GamepadDevice> Gamepad<D> {
+    /// Creates a gamepad reading from `device`, using the default stick deadzone.
+    pub fn new(device: D) -> Self {
+        Self {
+            device,
+            deadzone: DEFAULT_STICK_DEADZONE,
+            current: None,
+            previous: None,
+        }
+    }
+
+    /// Polls the device. Must be called once per frame, before actions are queried.
+    pub fn update(&mut self) {
+        self.previous = self.current.take();
+        self.current = self.device.poll();
+
+        match (&self.previous, &self.current) {
+            (None, Some(_)) => println!("Gamepad connected"),
+            (Some(_), None) => println!("Gamepad disconnected"),
+            _ => {}
+        }
+    }
+
+    /// Returns whether a gamepad is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Returns whether the action is held on the gamepad.
+    pub fn is_down(&self, action: Action) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|state| self.state_triggers(state, action))
+    }
+
+    /// Returns whether the action started being held during this frame.
+    pub fn is_pressed(&self, action: Action) -> bool {
+        let was_down = self
+            .previous
+            .as_ref()
+            .is_some_and(|state| self.state_triggers(state, action));
+        self.is_down(action) && !was_down
+    }
+
+    /// Returns whether a pad state triggers an action, through its buttons or the left stick.
+    fn state_triggers(&self, state: &PadState, action: Action) -> bool {
+        let stick = state.left_stick;
+        let stick = match action {
+            Action::MoveLeft => stick.x < -self.deadzone,
+            Action::MoveRight => stick.x > self.deadzone,
+            Action::MoveDown => stick.y > self.deadzone,
+            _ => false,
+        };
+        stick
+            || default_buttons(action)
+                .iter()
+                .any(|button| state.is_down(*button))
+    }
+}
+
+/// Creates the gamepad device for this build: `gilrs` when the `gamepad` feature is enabled,
+/// or a device that never reports a gamepad otherwise (or if `gilrs` fails to start).
+pub fn system_device() -> Box<dyn GamepadDevice> {
+    #[cfg(feature = "gamepad")]
+    match gilrs_device::GilrsGamepad::new() {
+        Ok(device) => return Box::new(device),
+        Err(err) => eprintln!("Gamepad support unavailable: {}", err),
+    }
+    Box::new(NoGamepad)
+}
+
+#[cfg(feature = "gamepad")]
+mod gilrs_device {
+    use super::{GamepadDevice, PadButton, PadState};
+    use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+    use macroquad::prelude::vec2;
+
+    const BUTTONS: [(Button, PadButton); 7] = [
+        (Button::South, PadButton::South),
+        (Button::East, PadButton::East),
+        (Button::West, PadButton::West),
+        (Button::Start, PadButton::Start),
+        (Button::DPadLeft, PadButton::DPadLeft),
+        (Button::DPadRight, PadButton::DPadRight),
+        (Button::DPadDown, PadButton::DPadDown),
+    ];
+
+    /// Reads the most recently connected (or used) hardware gamepad through `gilrs`.
+    pub struct GilrsGamepad {
+        gilrs: Gilrs,
+        active: Option<GamepadId>,
+    }
+
+    impl GilrsGamepad {
+        pub fn new() -> Result<Self, Box<gilrs::Error>> {
+            let gilrs = Gilrs::new().map_err(Box::new)?;
+            let active = gilrs.gamepads().next().map(|(id, _)| id);
+            Ok(Self { gilrs, active })
+        }
+    }
+
+    impl GamepadDevice for GilrsGamepad {
+        fn poll(&mut self) -> Option<PadState> {
+            while let Some(event) = self.gilrs.next_event() {
+                match event.event {
+                    EventType::Disconnected if self.active == Some(event.id) => {
+                        self.active = self.gilrs.gamepads().next().map(|(id, _)| id);
+                    }
+                    EventType::Disconnected | EventType::Dropped => {}
+                    _ => self.active = Some(event.id),
+                }
+            }
+
+            let gamepad = self.gilrs.connected_gamepad(self.active?)?;
+            Some(PadState {
+                buttons: BUTTONS
+                    .iter()
+                    .filter(|(button, _)| gamepad.is_pressed(*button))
+                    .map(|(_, pad_button)| *pad_button)
+                    .collect(),
+                // gilrs reports the y axis pointing up.
+                left_stick: vec2(
+                    gamepad.value(Axis::LeftStickX),
+                    -gamepad.value(Axis::LeftStickY),
+                ),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons_and_dpad_map_to_actions() {
+        let mut pad = Gamepad::new(VirtualGamepad {
+            connected: true,
+            ..Default::default()
+        });
+        pad.device.press(PadButton::DPadRight);
+        pad.device.press(PadButton::South);
+        pad.update();
+        assert!(pad.is_down(Action::MoveRight));
+        assert!(!pad.is_down(Action::MoveLeft));
+        assert!(pad.is_pressed(Action::Jump));
+        assert!(pad.is_pressed(Action::Confirm));
+        assert!(!pad.is_down(Action::GrabThrow));
+
+        // Holding the button does not trigger a new press on the next frame.
+        pad.update();
+        assert!(pad.is_down(Action::Jump));
+        assert!(!pad.is_pressed(Action::Jump));
+
+        pad.device.release(PadButton::South);
+        pad.update();
+        assert!(!pad.is_down(Action::Jump));
+    }
+
+    #[test]
+    fn stick_respects_the_deadzone() {
+        let mut pad = Gamepad::new(VirtualGamepad {
+            connected: true,
+            ..Default::default()
+        });
+        pad.device.state.left_stick = vec2(-0.2, 0.0);
+        pad.update();
+        assert!(!pad.is_down(Action::MoveLeft));
+
+        pad.device.state.left_stick = vec2(-0.8, 0.0);
+        pad.update();
+        assert!(pad.is_down(Action::MoveLeft));
+        assert!(!pad.is_down(Action::MoveRight));
+        assert!(!pad.is_down(Action::MoveDown));
+
+        pad.device.state.left_stick = vec2(0.1, 0.9);
+        pad.update();
+        assert!(pad.is_down(Action::MoveDown));
+        assert!(!pad.is_down(Action::MoveLeft));
+    }
+
+    #[test]
+    fn hot_plugging_resets_the_state() {
+        let mut pad = Gamepad::new(VirtualGamepad::default());
+        pad.device.press(PadButton::Start);
+        pad.update();
+        assert!(!pad.is_connected());
+        assert!(!pad.is_down(Action::Pause));
+
+        pad.device.connected = true;
+        pad.update();
+        assert!(pad.is_connected());
+        assert!(pad.is_pressed(Action::Pause));
+
+        pad.device.connected = false;
+        pad.update();
+        assert!(!pad.is_connected());
+        assert!(!pad.is_down(Action::Pause));
+    }
+}
@@ -0,0 +1,55 @@
+//! GUI Module
+//!
+//! This module draws the heads-up display shown at the top of the screen.
+
+use macroquad::prelude::*;
+
+const GUI_FONT_SIZE: f32 = 30.;
+const GUI_MARGIN: f32 = 20.;
+const HEALTH_PIP_SIZE: f32 = 12.;
+const HEALTH_PIP_GAP: f32 = 6.;
+
+/// Represents the in-game heads-up display.
+pub struct Gui;
+
+impl Gui {
+    /// Creates a new GUI instance.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Draws the GUI, showing the collected keys, the score, the remaining lives and, under
+    /// them, the health left in the current life.
+    pub fn draw(&self, keys_collected: u32, total_keys: u32, score: u32, lives: u32, health: u32) {
+        draw_text(
+            &format!("Keys: {}/{}", keys_collected, total_keys),
+            GUI_MARGIN,
+            GUI_MARGIN + GUI_FONT_SIZE / 2.,
+            GUI_FONT_SIZE,
+            WHITE,
+        );
+        draw_text(
+            &format!("Score: {}", score),
+            screen_width() / 2. - 60.,
+            GUI_MARGIN + GUI_FONT_SIZE / 2.,
+            GUI_FONT_SIZE,
+            WHITE,
+        );
+        draw_text(
+            &format!("Lives: {}", lives),
+            screen_width() - 160.,
+            GUI_MARGIN + GUI_FONT_SIZE / 2.,
+            GUI_FONT_SIZE,
+            WHITE,
+        );
+        for i in 0..health {
+            draw_rectangle(
+                screen_width() - 160. + i as f32 * (HEALTH_PIP_SIZE + HEALTH_PIP_GAP),
+                GUI_MARGIN + GUI_FONT_SIZE / 2. + HEALTH_PIP_GAP,
+                HEALTH_PIP_SIZE,
+                HEALTH_PIP_SIZE,
+                RED,
+            );
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! High Scores Module
+//!
+//! This module keeps the table of the best runs, saved to a file in the user's data directory
+//! (`$XDG_DATA_HOME`, or `~/.local/share`, on Linux).
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{HIGH_SCORES_FILE_NAME, MAX_HIGH_SCORES};
+use crate::input::ConfigError;
+use macroquad::prelude::*;
+
+/// A run in the high-score table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    /// The level the run ended on, starting at 1.
+    pub level: u32,
+    /// How long the run lasted, in seconds.
+    pub time: f32,
+    /// The seed the run was generated from, to play it again.
+    pub seed: u64,
+}
+
+/// The best runs, from the highest score down.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Loads the table from `path`. A missing file is not an error and yields an empty table.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError {
+                line: None,
+                message: format!("cannot read {}: {}", path.as_ref().display(), err),
+            }),
+        }
+    }
+
+    /// Parses the table from the contents of a high-score file: one run per line, as
+    /// `score level time seed name`. The name comes last, so it can contain spaces.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let mut high_scores = Self::default();
+        for (index, raw_line) in contents.lines().enumerate() {
+            let error = |message: String| ConfigError {
+                line: Some(index + 1),
+                message,
+            };
+
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.splitn(5, ' ');
+            let mut field = |what: &str| {
+                fields
+                    .next()
+                    .filter(|field| !field.is_empty())
+                    .ok_or_else(|| error(format!("missing {}", what)))
+            };
+            let score = field("score")?;
+            let level = field("level")?;
+            let time = field("time")?;
+            let seed = field("seed")?;
+            let name = field("name")?;
+            let invalid = |what: &str, value: &str| error(format!("invalid {} `{}`", what, value));
+            high_scores.entries.push(HighScore {
+                name: name.trim().to_owned(),
+                score: score.parse().map_err(|_| invalid("score", score))?,
+                level: level.parse().map_err(|_| invalid("level", level))?,
+                time: time.parse().map_err(|_| invalid("time", time))?,
+                seed: seed.parse().map_err(|_| invalid("seed", seed))?,
+            });
+        }
+        high_scores
+            .entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        high_scores.entries.truncate(MAX_HIGH_SCORES);
+        Ok(high_scores)
+    }
+
+    /// Writes the table to `path`, creating its directory if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_file_string())
+    }
+
+    /// Formats the table in the high-score file format.
+    pub fn to_file_string(&self) -> String {
+        let mut contents = String::from("# Platformer high scores: score level time seed name\n");
+        for entry in &self.entries {
+            contents.push_str(&format!(
+                "{} {} {:.1} {} {}\n",
+                entry.score, entry.level, entry.time, entry.seed, entry.name
+            ));
+        }
+        contents
+    }
+
+    /// Returns whether a run with `score` points makes it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_HIGH_SCORES
+                || self.entries.last().is_some_and(|last| score > last.score))
+    }
+
+    /// Adds a run to the table, dropping the lowest one if the table is full, and returns its
+    /// rank, or `None` if it did not make it into the table. A run ties below the runs that
+    /// already have the same score.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_HIGH_SCORES);
+        Some(rank)
+    }
+
+    /// Draws the table with its top at `y`, highlighting the run at rank `highlight`.
+    pub fn draw(&self, y: f32, highlight: Option<usize>) {
+        let x = screen_width() / 2. - 300.;
+        draw_text("HIGH SCORES", x, y, 30., WHITE);
+        if self.entries.is_empty() {
+            draw_text("No runs yet", x, y + 40., 20., GRAY);
+        }
+        for (rank, entry) in self.entries.iter().enumerate() {
+            let row_y = y + 40. + rank as f32 * 24.;
+            let color = if highlight == Some(rank) {
+                YELLOW
+            } else {
+                WHITE
+            };
+            let columns = [
+                (0., format!("{:>2}.", rank + 1)),
+                (50., entry.name.clone()),
+                (240., entry.score.to_string()),
+                (340., format!("Level {}", entry.level)),
+                (440., format!("{:.0}s", entry.time)),
+                (510., format!("Seed {}", entry.seed)),
+            ];
+            for (column_x, text) in columns {
+                draw_text(&text, x + column_x, row_y, 20., color);
+            }
+        }
+    }
+}
+
+/// Returns where the high scores are saved: in the `platformer` directory of the user's data
+/// directory, or `None` if there is no home directory to find it in.
+pub fn default_path() -> Option<PathBuf> {
+    let data_dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        xdg_data_dir(env::var_os("XDG_DATA_HOME"), env::var_os("HOME"))
+    };
+    data_dir.map(|dir| dir.join("platformer").join(HIGH_SCORES_FILE_NAME))
+}
+
+/// Returns the XDG data directory: `$XDG_DATA_HOME` if it is an absolute path, as the
+/// specification requires, or `$HOME/.local/share`.
+fn xdg_data_dir(xdg_data_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    xdg_data_home
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| PathBuf::from(home).join(".local/share")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(name: &str, score: u32) -> HighScore {
+        HighScore {
+            name: name.to_owned(),
+            score,
+            level: 2,
+            time: 93.5,
+            seed: 42,
+        }
+    }
+
+    #[test]
+    fn the_table_keeps_the_ten_best_runs_in_order() {
+        let mut high_scores = HighScores::default();
+        for score in 1..=MAX_HIGH_SCORES as u32 {
+            assert_eq!(high_scores.insert(run("AAA", score * 100)), Some(0));
+        }
+        assert!(!high_scores.qualifies(100));
+        assert_eq!(high_scores.insert(run("LOW", 50)), None);
+        // A tie goes below the run that got there first.
+        assert_eq!(high_scores.insert(run("TIE", 500)), Some(6));
+        // The lowest run was dropped to make room: beating the next one is now needed.
+        assert!(!high_scores.qualifies(200));
+        assert!(high_scores.qualifies(201));
+        assert!(!high_scores.qualifies(0));
+
+        let contents = high_scores.to_file_string();
+        let lines: Vec<&str> = contents
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        assert_eq!(lines.len(), MAX_HIGH_SCORES);
+        assert!(lines[0].starts_with("1000 "));
+        assert!(lines[6].ends_with(" TIE"));
+        assert!(lines[MAX_HIGH_SCORES - 1].starts_with("200 "));
+    }
+
+    #[test]
+    fn the_file_format_round_trips_and_reports_bad_lines() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(run("Jane Doe", 1200));
+        high_scores.insert(run("BOB", 800));
+        let parsed = HighScores::parse(&high_scores.to_file_string()).unwrap();
+        assert_eq!(parsed, high_scores);
+
+        let err = HighScores::parse("1200 2 93.5 42 BOB\n800 two 10.0 1 ANN\n").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert_eq!(err.message, "invalid level `two`");
+        let err = HighScores::parse("1200 2 93.5 42\n").unwrap_err();
+        assert_eq!(err.message, "missing name");
+    }
+
+    #[test]
+    fn the_data_directory_follows_the_xdg_specification() {
+        let home = Some(OsString::from("/home/jane"));
+        assert_eq!(
+            xdg_data_dir(Some("/data".into()), home.clone()),
+            Some(PathBuf::from("/data"))
+        );
+        assert_eq!(
+            xdg_data_dir(Some("relative".into()), home.clone()),
+            Some(PathBuf::from("/home/jane/.local/share"))
+        );
+        assert_eq!(
+            xdg_data_dir(None, home),
+            Some(PathBuf::from("/home/jane/.local/share"))
+        );
+        assert_eq!(xdg_data_dir(None, None), None);
+    }
+}
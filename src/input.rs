@@ -0,0 +1,409 @@
+//! Input Module
+//!
+//! This module maps physical keys to game actions and captures the player's input for a
+//! single frame (from the keyboard or, through the same actions, a gamepad), so that the
+//! simulation can be driven without reading the keyboard directly.
+//! Bindings can be loaded from and saved to a simple configuration file, one action per line:
+//!
+//! ```text
+//! # Comments start with a hash sign.
+//! move_left = Left, A
+//! jump = Up, W
+//! ```
+
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// The game actions that can be bound to keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveDown,
+    Jump,
+    GrabThrow,
+    Confirm,
+    Pause,
+}
+
+impl Action {
+    /// All actions, in the order they are listed on the rebinding screen and in the config file.
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveDown,
+        Action::Jump,
+        Action::GrabThrow,
+        Action::Confirm,
+        Action::Pause,
+    ];
+
+    /// Returns the name used for this action in the config file.
+    pub fn config_name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::MoveDown => "move_down",
+            Action::Jump => "jump",
+            Action::GrabThrow => "grab_throw",
+            Action::Confirm => "confirm",
+            Action::Pause => "pause",
+        }
+    }
+
+    /// Returns the human-readable label shown on the rebinding screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveDown => "Move down",
+            Action::Jump => "Jump",
+            Action::GrabThrow => "Grab / Throw",
+            Action::Confirm => "Confirm",
+            Action::Pause => "Pause",
+        }
+    }
+
+    /// Looks up an action from its config file name.
+    fn from_config_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.config_name() == name)
+    }
+}
+
+/// The keys that can be named in the config file.
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Pause,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpEnter,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+];
+
+/// Returns the name of a key, as written in the config file (e.g. `Left`, `Space`, `A`).
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// Looks up a bindable key from its config file name, ignoring case.
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| key_name(*key).eq_ignore_ascii_case(name))
+}
+
+/// An error found while reading a controls config file or the high-score file.
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    /// The 1-based line the error was found on, if it relates to a specific line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Maps each action to the keys that trigger it. An action can have several bindings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for InputMap {
+    /// The default controls: arrow keys (or WASD) to move and jump, SPACE to grab and throw.
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::MoveLeft, vec![KeyCode::Left, KeyCode::A]);
+        bindings.insert(Action::MoveRight, vec![KeyCode::Right, KeyCode::D]);
+        bindings.insert(Action::MoveDown, vec![KeyCode::Down, KeyCode::S]);
+        bindings.insert(Action::Jump, vec![KeyCode::Up, KeyCode::W]);
+        bindings.insert(Action::GrabThrow, vec![KeyCode::Space]);
+        bindings.insert(Action::Confirm, vec![KeyCode::Enter]);
+        bindings.insert(Action::Pause, vec![KeyCode::Escape, KeyCode::P]);
+        Self { bindings }
+    }
+}
+
+impl InputMap {
+    /// Loads the bindings from `path`. Actions missing from the file keep their default keys.
+    /// A missing file is not an error and yields the default bindings.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(contents) => Self::parse(&contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError {
+                line: None,
+                message: format!("cannot read {}: {}", path.as_ref().display(), err),
+            }),
+        }
+    }
+
+    /// Parses bindings from the contents of a config file.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let mut map = Self::default();
+        for (index, raw_line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| ConfigError {
+                line: Some(line_number),
+                message,
+            };
+
+            let line = raw_line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, keys) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `action = keys`, found `{}`", line)))?;
+            let action = Action::from_config_name(name.trim())
+                .ok_or_else(|| error(format!("unknown action `{}`", name.trim())))?;
+
+            let mut bound_keys = Vec::new();
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                let key =
+                    key_from_name(key).ok_or_else(|| error(format!("unknown key `{}`", key)))?;
+                if !bound_keys.contains(&key) {
+                    bound_keys.push(key);
+                }
+            }
+            map.bindings.insert(action, bound_keys);
+        }
+        Ok(map)
+    }
+
+    /// Writes the bindings to `path` in the config file format.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_config_string())
+    }
+
+    /// Formats the bindings in the config file format.
+    pub fn to_config_string(&self) -> String {
+        let mut contents = String::from("# Platformer controls: action = key, key, ...\n");
+        for action in Action::ALL {
+            let keys: Vec<String> = self.keys(action).iter().map(|k| key_name(*k)).collect();
+            contents.push_str(&format!("{} = {}\n", action.config_name(), keys.join(", ")));
+        }
+        contents
+    }
+
+    /// Returns the keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Describes the keys bound to an action for on-screen prompts, e.g. `ENTER` or `UP/W`.
+    pub fn describe(&self, action: Action) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return "(unbound)".to_owned();
+        }
+        keys.iter()
+            .map(|key| key_name(*key).to_uppercase())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Adds a key binding to an action, if it is not already bound to it.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        let keys = self.bindings.entry(action).or_default();
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    /// Removes all key bindings from an action.
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.insert(action, Vec::new());
+    }
+
+    /// Returns whether any key bound to the action is held down.
+    pub fn is_down(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|key| is_key_down(*key))
+    }
+
+    /// Returns whether any key bound to the action was pressed during this frame.
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|key| is_key_pressed(*key))
+    }
+}
+
+/// A snapshot of the player's input for one simulation step.
+///
+/// Movement fields report whether an action is held, while `jump` and `grab_throw`
+/// report whether the action was triggered during this frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct InputFrame {
+    pub left: bool,
+    pub right: bool,
+    /// Held with `jump` to drop through a one-way platform.
+    pub down: bool,
+    pub jump: bool,
+    pub grab_throw: bool,
+}
+
+impl InputFrame {
+    /// Builds an input frame from action queries: `down` reports whether an action is held
+    /// and `pressed` whether it was triggered during this frame.
+    pub fn from_actions(down: impl Fn(Action) -> bool, pressed: impl Fn(Action) -> bool) -> Self {
+        Self {
+            left: down(Action::MoveLeft),
+            right: down(Action::MoveRight),
+            down: down(Action::MoveDown),
+            jump: pressed(Action::Jump),
+            grab_throw: pressed(Action::GrabThrow),
+        }
+    }
+
+    /// Merges a newer frame into this one: held keys are replaced while presses are kept
+    /// until they are consumed by `clear_presses`, so that no press is lost when a frame
+    /// runs no simulation step.
+    pub fn latch(&mut self, newer: &InputFrame) {
+        self.left = newer.left;
+        self.right = newer.right;
+        self.down = newer.down;
+        self.jump |= newer.jump;
+        self.grab_throw |= newer.grab_throw;
+    }
+
+    /// Clears the one-shot presses once a simulation step has consumed them.
+    pub fn clear_presses(&mut self) {
+        self.jump = false;
+        self.grab_throw = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_multiple_bindings_and_keeps_defaults_for_missing_actions() {
+        let map = InputMap::parse("# custom\njump = Space, Z\nmove_left = q # azerty\n").unwrap();
+        assert_eq!(map.keys(Action::Jump), &[KeyCode::Space, KeyCode::Z]);
+        assert_eq!(map.keys(Action::MoveLeft), &[KeyCode::Q]);
+        assert_eq!(
+            map.keys(Action::MoveRight),
+            InputMap::default().keys(Action::MoveRight)
+        );
+    }
+
+    #[test]
+    fn reports_the_offending_line() {
+        let err = InputMap::parse("jump = Up\n\nfly = Space\n").unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert!(err.message.contains("fly"));
+
+        let err = InputMap::parse("jump = Upp\n").unwrap_err();
+        assert_eq!(err.line, Some(1));
+        assert!(err.message.contains("Upp"));
+    }
+
+    #[test]
+    fn config_round_trips() {
+        let mut map = InputMap::default();
+        map.clear(Action::GrabThrow);
+        map.bind(Action::GrabThrow, KeyCode::X);
+        map.bind(Action::GrabThrow, KeyCode::LeftControl);
+        let parsed = InputMap::parse(&map.to_config_string()).unwrap();
+        assert_eq!(parsed, map);
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! This module defines the items that appear in the game world.
 
+use crate::body::{Body, BodyOptions};
 use crate::constants::{
-    GRAVITY, ITEM_HOOKED_COLOR, ITEM_IDLE_COLOR, ITEM_SIZE, ITEM_THROWN_COLOR,
+    ITEM_BOUNCE_ENERGY_LOSS, ITEM_HOOKED_COLOR, ITEM_IDLE_COLOR, ITEM_MIN_BOUNCE_SPEED, ITEM_SIZE,
+    ITEM_THROWN_COLOR,
 };
 use macroquad::prelude::*;
 
@@ -17,10 +19,7 @@ pub enum ItemState {
 
 /// Represents an item in the game world.
 pub struct Item {
-    pub position: Vec2,
-    pub size: Vec2,
-    pub velocity: Vec2,
-    pub on_ground: bool,
+    pub body: Body,
     pub state: ItemState,
 }
 
@@ -28,40 +27,47 @@ impl Item {
     /// Creates a new item at a specific position.
     pub fn new(pos: Vec2) -> Self {
         Self {
-            position: pos,
-            size: vec2(ITEM_SIZE, ITEM_SIZE),
-            velocity: Vec2::ZERO,
-            on_ground: false,
+            body: Body::new(pos, vec2(ITEM_SIZE, ITEM_SIZE)),
             state: ItemState::Idle,
         }
     }
 
-    /// Returns the item's bounding box as a `Rect`.
-    pub fn rect(&self) -> Rect {
-        Rect::new(self.position.x, self.position.y, self.size.x, self.size.y)
+    /// Returns how the collision pipeline treats the item: thrown items bounce until they slow
+    /// down, and only idle items can be picked up.
+    pub fn body_options(&self) -> BodyOptions {
+        match self.state {
+            ItemState::Thrown => BodyOptions {
+                bounce: ITEM_BOUNCE_ENERGY_LOSS,
+                min_bounce_speed: ITEM_MIN_BOUNCE_SPEED,
+                friction: ITEM_BOUNCE_ENERGY_LOSS,
+                holdable: false,
+                slides: true,
+            },
+            ItemState::Idle | ItemState::Hooked => BodyOptions {
+                bounce: 0.0,
+                min_bounce_speed: 0.0,
+                friction: 1.0,
+                holdable: self.state == ItemState::Idle,
+                slides: true,
+            },
+        }
     }
 
     /// Updates the item's state, applying gravity only if it's not on the ground.
     pub fn update(&mut self, dt: f32) {
-        if !self.on_ground {
-            self.velocity.y += GRAVITY * dt;
-            self.position += self.velocity * dt;
+        if !self.body.on_ground {
+            self.body.apply_gravity(dt);
+            self.body.advance(dt);
         }
     }
 
-    /// Draws the item on the screen.
-    pub fn draw(&self) {
+    /// Draws the item on the screen, interpolated by `alpha` between its last two positions.
+    pub fn draw(&self, alpha: f32) {
         let color = match self.state {
             ItemState::Idle => ITEM_IDLE_COLOR,
             ItemState::Hooked => ITEM_HOOKED_COLOR,
             ItemState::Thrown => ITEM_THROWN_COLOR,
         };
-        draw_rectangle(
-            self.position.x,
-            self.position.y,
-            self.size.x,
-            self.size.y,
-            color,
-        );
+        self.body.draw(alpha, color);
     }
 }
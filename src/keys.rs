@@ -0,0 +1,31 @@
+//! Keys Module
+//!
+//! This module defines the collectible keys that the player must gather.
+
+use crate::constants::KEY_COLOR;
+use macroquad::prelude::*;
+
+/// Represents a collectible key in the game world.
+pub struct Key {
+    pub rect: Rect,
+}
+
+impl Key {
+    /// Creates a new key at a specific position with the given size.
+    pub fn new(pos: Vec2, size: f32) -> Self {
+        Self {
+            rect: Rect::new(pos.x, pos.y, size, size),
+        }
+    }
+
+    /// Draws the key on the screen.
+    pub fn draw(&self) {
+        draw_rectangle(
+            self.rect.x,
+            self.rect.y,
+            self.rect.w,
+            self.rect.h,
+            KEY_COLOR,
+        );
+    }
+}
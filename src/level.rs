@@ -1,125 +1,273 @@
 //! Level Module
 //!
-//! This module defines the game world's structure and layout.
+//! This module defines the game world's structure and layout, built from a level file.
 
+use crate::arena::Arena;
 use crate::blocks::Block;
 use crate::constants::*;
 use crate::items::Item;
 use crate::keys::Key;
+use crate::level_file::{LevelFile, point};
+use crate::physics::Solidity;
+use crate::platforms::{PlatformPath, Waypoint};
 use macroquad::prelude::*;
-use macroquad::rand;
 
-pub const LEVEL_WIDTH: f32 = 2. * 1024.;
-pub const LEVEL_HEIGHT: f32 = 2. * 768.;
+/// Represents a platform of the level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Platform {
+    pub rect: Rect,
+    /// Whether the platform blocks bodies on all sides or only from above.
+    pub solidity: Solidity,
+    /// The path a moving platform follows, or `None` for a platform that stays put.
+    pub path: Option<PlatformPath>,
+    /// The position at the start of the last simulation step, used to interpolate rendering.
+    pub previous_position: Vec2,
+    /// Whether the platform is left to the level's tiles to draw.
+    pub hidden: bool,
+}
+
+impl Platform {
+    /// Creates a platform that stays put.
+    pub fn new(rect: Rect, solidity: Solidity) -> Self {
+        Self {
+            rect,
+            solidity,
+            path: None,
+            previous_position: rect.point(),
+            hidden: false,
+        }
+    }
+
+    /// Creates a platform of the given size that moves along `path`, from its first waypoint.
+    pub fn moving(size: Vec2, solidity: Solidity, path: PlatformPath) -> Self {
+        let start = path.start().unwrap_or_default();
+        Self {
+            path: Some(path),
+            ..Self::new(Rect::new(start.x, start.y, size.x, size.y), solidity)
+        }
+    }
+
+    /// Returns the platform's bounds interpolated between the last two simulation steps.
+    pub fn render_rect(&self, alpha: f32) -> Rect {
+        let position = self.previous_position.lerp(self.rect.point(), alpha);
+        Rect::new(position.x, position.y, self.rect.w, self.rect.h)
+    }
+}
+
+/// Represents a ramp: a right triangle standing on the bottom of `rect`, whose hypotenuse can
+/// be walked along. Like one-way platforms, slopes are only stood on from above.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slope {
+    pub rect: Rect,
+    /// Whether the slope goes up from left to right.
+    pub rises_right: bool,
+}
+
+impl Slope {
+    /// Returns the height of the sloped surface at `x`, clamped to the ends of the slope.
+    pub fn surface_at(&self, x: f32) -> f32 {
+        let along = ((x - self.rect.left()) / self.rect.w).clamp(0., 1.);
+        let rise = if self.rises_right { along } else { 1. - along };
+        self.rect.bottom() - rise * self.rect.h
+    }
+
+    /// Returns the height of the surface under a body with bounds `rect`. The body rests on its
+    /// uphill bottom corner, the only one touching the slope.
+    pub fn surface_under(&self, rect: &Rect) -> f32 {
+        self.surface_at(if self.rises_right {
+            rect.right()
+        } else {
+            rect.left()
+        })
+    }
+
+    /// Returns the vertical change of the surface per pixel travelled to the right.
+    pub fn gradient(&self) -> f32 {
+        let gradient = self.rect.h / self.rect.w;
+        if self.rises_right {
+            -gradient
+        } else {
+            gradient
+        }
+    }
+}
+
+/// A tile of the world tileset, drawn over `rect`.
+pub struct Tile {
+    pub rect: Rect,
+    /// The index of the tile in the tileset, row by row.
+    pub index: u32,
+}
+
+impl Tile {
+    /// Returns the part of the tileset texture the tile is drawn from.
+    pub fn source(&self) -> Rect {
+        let column = self.index % TILESET_COLUMNS;
+        let row = self.index / TILESET_COLUMNS;
+        Rect::new(
+            column as f32 * TILESET_TILE_SIZE,
+            row as f32 * TILESET_TILE_SIZE,
+            TILESET_TILE_SIZE,
+            TILESET_TILE_SIZE,
+        )
+    }
+}
+
+/// A flag that becomes the player's respawn point once they touch it.
+pub struct Checkpoint {
+    pub rect: Rect,
+    pub reached: bool,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint standing on the surface at `foot`, the middle of its bottom edge.
+    pub fn new(foot: Vec2) -> Self {
+        Self {
+            rect: Rect::new(
+                foot.x - CHECKPOINT_WIDTH / 2.,
+                foot.y - CHECKPOINT_HEIGHT,
+                CHECKPOINT_WIDTH,
+                CHECKPOINT_HEIGHT,
+            ),
+            reached: false,
+        }
+    }
+
+    /// Returns where a player of the given size respawns: above the checkpoint's foot.
+    pub fn respawn_point(&self, player_size: Vec2) -> Vec2 {
+        vec2(
+            self.rect.center().x - player_size.x / 2.,
+            self.rect.bottom() - player_size.y,
+        )
+    }
+}
+
+/// The exit of a level. It opens once every key is collected, and walking into it then
+/// completes the level.
+pub struct Door {
+    pub rect: Rect,
+}
+
+impl Door {
+    /// Creates a door standing on the surface at `foot`, the middle of its bottom edge.
+    pub fn new(foot: Vec2) -> Self {
+        Self {
+            rect: Rect::new(
+                foot.x - DOOR_WIDTH / 2.,
+                foot.y - DOOR_HEIGHT,
+                DOOR_WIDTH,
+                DOOR_HEIGHT,
+            ),
+        }
+    }
+}
 
 /// Represents the game level, including its boundaries and platforms.
 pub struct Level {
+    /// The width and height of the level, boundaries included.
+    pub size: Vec2,
     pub ground: Rect,
     pub ceiling: Rect,
     pub left_wall: Rect,
     pub right_wall: Rect,
-    pub platforms: Vec<Rect>,
-    pub items: Vec<Item>,
-    pub blocks: Vec<Block>,
-    pub keys: Vec<Key>,
+    pub platforms: Vec<Platform>,
+    pub slopes: Vec<Slope>,
+    pub tiles: Vec<Tile>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub exit: Door,
+    pub items: Arena<Item>,
+    pub blocks: Arena<Block>,
+    pub keys: Arena<Key>,
     pub total_keys: u32,
 }
 
 impl Level {
-    /// Creates a new level instance, populating it with platforms and defining its boundaries.
-    pub async fn new() -> Self {
-        let mut platforms = vec![];
-        let screen_width = 1024.;
-        let screen_height = 768.;
-
-        for i in 0..2 {
-            // columns
-            for j in 0..2 {
-                // rows
-                let offset_x = i as f32 * screen_width;
-                let offset_y = j as f32 * screen_height;
-
-                // Define the platform layout relative to a screen's top-left corner
-                let base_platforms = vec![
-                    Rect::new(200., 120., 200., 20.),
-                    Rect::new(500., 360., 200., 20.),
-                    Rect::new(800., 568., 200., 20.),
-                ];
-
-                for platform in &base_platforms {
-                    platforms.push(Rect::new(
-                        offset_x + platform.x,
-                        offset_y + platform.y,
-                        platform.w,
-                        platform.h,
-                    ));
-                }
-            }
-        }
-
-        let mut items = vec![];
-        for _ in 0..ITEM_COUNT {
-            items.push(Item::new(vec2(
-                rand::gen_range(WALL_WIDTH, LEVEL_WIDTH - WALL_WIDTH - ITEM_SIZE),
-                rand::gen_range(CEILING_HEIGHT, LEVEL_HEIGHT - GROUND_HEIGHT - ITEM_SIZE),
-            )));
-        }
-
-        let mut blocks = vec![];
-        let player_spawn_rect = Rect::new(
-            PLAYER_SPAWN_X,
-            LEVEL_HEIGHT - GROUND_HEIGHT - PLAYER_SIZE,
-            PLAYER_SIZE,
-            PLAYER_SIZE,
-        );
-        let safe_zone_margin = (PLAYER_SIZE * PLAYER_SAFE_ZONE_MULTIPLIER - PLAYER_SIZE) / 2.0;
-        let player_safe_zone = Rect::new(
-            player_spawn_rect.x - safe_zone_margin,
-            CEILING_HEIGHT,
-            player_spawn_rect.w + safe_zone_margin * 2.0,
-            LEVEL_HEIGHT - GROUND_HEIGHT - CEILING_HEIGHT,
-        );
+    /// Creates a level from its description in a level file, with every object at its spawn
+    /// point.
+    pub fn new(file: &LevelFile) -> Self {
+        let size = point(file.size);
+        let bounds = file.boundaries;
 
-        for _ in 0..BLOCK_COUNT {
-            let mut block_pos;
-            loop {
-                block_pos = vec2(
-                    rand::gen_range(WALL_WIDTH, LEVEL_WIDTH - WALL_WIDTH - BLOCK_SIZE),
-                    rand::gen_range(CEILING_HEIGHT, LEVEL_HEIGHT - GROUND_HEIGHT - BLOCK_SIZE),
-                );
-                let block_rect = Rect::new(block_pos.x, block_pos.y, BLOCK_SIZE, BLOCK_SIZE);
-                if !block_rect.overlaps(&player_safe_zone) {
-                    break;
+        let platforms = file
+            .platforms
+            .iter()
+            .map(|spec| {
+                let rect = spec.rect.0;
+                let platform = match &spec.path {
+                    Some(path) => {
+                        let wait = path.wait.unwrap_or(MOVING_PLATFORM_WAIT);
+                        let waypoints = std::iter::once(rect.point())
+                            .chain(path.to.iter().copied().map(point))
+                            .map(|position| Waypoint { position, wait })
+                            .collect();
+                        Platform::moving(
+                            rect.size(),
+                            spec.solidity,
+                            PlatformPath::new(
+                                waypoints,
+                                path.mode,
+                                path.speed.unwrap_or(MOVING_PLATFORM_SPEED),
+                            ),
+                        )
+                    }
+                    None => Platform::new(rect, spec.solidity),
+                };
+                Platform {
+                    hidden: spec.hidden,
+                    ..platform
                 }
-            }
-            blocks.push(Block::new(block_pos));
-        }
+            })
+            .collect();
+        let slopes = file
+            .slopes
+            .iter()
+            .map(|spec| Slope {
+                rect: spec.rect.0,
+                rises_right: spec.rises_right,
+            })
+            .collect();
+        let tiles = file
+            .tiles
+            .iter()
+            .map(|spec| Tile {
+                rect: spec.rect.0,
+                index: spec.tile,
+            })
+            .collect();
+        let checkpoints = file
+            .checkpoints
+            .iter()
+            .map(|&foot| Checkpoint::new(point(foot)))
+            .collect();
 
-        let mut keys = vec![];
-        let key_size = PLAYER_SIZE * 1.2;
-        for i in 0..2 {
-            // columns
-            for j in 0..2 {
-                // rows
-                let offset_x = i as f32 * screen_width;
-                let offset_y = j as f32 * screen_height;
-                keys.push(Key::new(
-                    vec2(
-                        offset_x + screen_width * 0.95 - key_size / 2.0,
-                        offset_y + screen_height * 0.15 - key_size / 2.0,
-                    ),
-                    key_size,
-                ));
-            }
-        }
+        let items = file
+            .items
+            .iter()
+            .map(|&position| Item::new(point(position)))
+            .collect();
+        let blocks = file
+            .blocks
+            .iter()
+            .map(|&position| Block::new(point(position)))
+            .collect();
+        let keys: Arena<Key> = file
+            .keys
+            .iter()
+            .map(|&position| Key::new(point(position), KEY_SIZE))
+            .collect();
         let total_keys = keys.len() as u32;
 
         Self {
-            ground: Rect::new(0., LEVEL_HEIGHT - GROUND_HEIGHT, LEVEL_WIDTH, GROUND_HEIGHT),
-            ceiling: Rect::new(0., 0., LEVEL_WIDTH, CEILING_HEIGHT),
-            left_wall: Rect::new(0., 0., WALL_WIDTH, LEVEL_HEIGHT),
-            right_wall: Rect::new(LEVEL_WIDTH - WALL_WIDTH, 0., WALL_WIDTH, LEVEL_HEIGHT),
+            size,
+            ground: Rect::new(0., size.y - bounds.ground, size.x, bounds.ground),
+            ceiling: Rect::new(0., 0., size.x, bounds.ceiling),
+            left_wall: Rect::new(0., 0., bounds.walls, size.y),
+            right_wall: Rect::new(size.x - bounds.walls, 0., bounds.walls, size.y),
             platforms,
+            slopes,
+            tiles,
+            checkpoints,
+            exit: Door::new(point(file.exit)),
             items,
             blocks,
             keys,
@@ -127,8 +275,14 @@ impl Level {
         }
     }
 
-    /// Draws the level, including boundaries and platforms.
-    pub fn draw(&self) {
+    /// Returns whether the exit is open: every key has been collected.
+    pub fn exit_is_open(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Draws the level, including boundaries and platforms. Moving objects are interpolated by
+    /// `alpha`. Tiles are drawn from `tileset`, or as plain rectangles if it could not be loaded.
+    pub fn draw(&self, alpha: f32, tileset: Option<&Texture2D>) {
         // Draw bounds
         draw_rectangle(
             self.ground.x,
@@ -159,30 +313,84 @@ impl Level {
             BOUNDS_COLOR,
         );
 
+        // Draw tiles
+        for tile in &self.tiles {
+            let rect = tile.rect;
+            match tileset {
+                Some(texture) => draw_texture_ex(
+                    texture,
+                    rect.x,
+                    rect.y,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(rect.size()),
+                        source: Some(tile.source()),
+                        ..Default::default()
+                    },
+                ),
+                None => draw_rectangle(rect.x, rect.y, rect.w, rect.h, SOLID_PLATFORM_COLOR),
+            }
+        }
+
         // Draw platforms
-        for platform in &self.platforms {
-            draw_rectangle(
-                platform.x,
-                platform.y,
-                platform.w,
-                platform.h,
+        for platform in self.platforms.iter().filter(|platform| !platform.hidden) {
+            let color = match platform.solidity {
+                Solidity::Solid => SOLID_PLATFORM_COLOR,
+                Solidity::OneWay => PLATFORM_COLOR,
+            };
+            let rect = platform.render_rect(alpha);
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+        }
+
+        // Draw slopes
+        for slope in &self.slopes {
+            let rect = slope.rect;
+            let peak_x = if slope.rises_right {
+                rect.right()
+            } else {
+                rect.left()
+            };
+            draw_triangle(
+                vec2(rect.left(), rect.bottom()),
+                vec2(rect.right(), rect.bottom()),
+                vec2(peak_x, rect.top()),
                 PLATFORM_COLOR,
             );
         }
 
+        // Draw the exit
+        let color = if self.exit_is_open() {
+            DOOR_OPEN_COLOR
+        } else {
+            DOOR_LOCKED_COLOR
+        };
+        let rect = self.exit.rect;
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+
+        // Draw checkpoints
+        for checkpoint in &self.checkpoints {
+            let color = if checkpoint.reached {
+                CHECKPOINT_REACHED_COLOR
+            } else {
+                CHECKPOINT_COLOR
+            };
+            let rect = checkpoint.rect;
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+        }
+
         // Draw blocks
-        for block in &self.blocks {
-            block.draw();
+        for block in self.blocks.values() {
+            block.draw(alpha);
         }
 
         // Draw keys
-        for key in &self.keys {
+        for key in self.keys.values() {
             key.draw();
         }
 
         // Draw items
-        for item in &self.items {
-            item.draw();
+        for item in self.items.values() {
+            item.draw(alpha);
         }
     }
 }
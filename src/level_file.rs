@@ -0,0 +1,434 @@
+//! Level File Module
+//!
+//! This module describes a level the way it is written in a level file: a human-editable RON
+//! document listing the level's size, boundaries, platforms and slopes, and where the player,
+//! the exit, the checkpoints, keys, blocks, items and baddies stand. `Level` is built from it.
+//!
+//! Positions are the top-left corner of what spawns there, except for the exit and the
+//! checkpoints, which are placed by the middle of their bottom edge, where they stand.
+//!
+//! A level can also be sketched as an ASCII grid, where each character covers one cell of the
+//! level, and converted into a `LevelFile`, and maps made with the Tiled editor are imported
+//! by the `tiled` module.
+
+use std::fs;
+use std::path::Path;
+
+use crate::constants::{
+    BADDIE_SIZE, BLOCK_SIZE, ITEM_SIZE, KEY_SIZE, LEVEL_GRID_CELL_SIZE, LEVEL_GRID_PLATFORM_HEIGHT,
+    PLAYER_SIZE,
+};
+use crate::input::ConfigError;
+use crate::physics::Solidity;
+use crate::platforms::PathMode;
+use crate::tiled;
+use macroquad::prelude::*;
+use ron::extensions::Extensions;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+/// A rectangle, written as `(x, y, width, height)`. Its width and height must be positive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "(f32, f32, f32, f32)", into = "(f32, f32, f32, f32)")]
+pub struct Area(pub Rect);
+
+impl TryFrom<(f32, f32, f32, f32)> for Area {
+    type Error = String;
+
+    fn try_from((x, y, w, h): (f32, f32, f32, f32)) -> Result<Self, Self::Error> {
+        if w > 0. && h > 0. {
+            Ok(Self(Rect::new(x, y, w, h)))
+        } else {
+            Err(format!("invalid size {} x {}: it must be positive", w, h))
+        }
+    }
+}
+
+impl From<Area> for (f32, f32, f32, f32) {
+    fn from(Area(rect): Area) -> Self {
+        (rect.x, rect.y, rect.w, rect.h)
+    }
+}
+
+/// The thickness of the solid bounds around the level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Boundaries {
+    pub ground: f32,
+    pub ceiling: f32,
+    pub walls: f32,
+}
+
+/// The path of a moving platform. The platform starts where it is placed and then visits the
+/// waypoints listed in `to`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathSpec {
+    pub mode: PathMode,
+    pub to: Vec<(f32, f32)>,
+    /// The platform's speed in pixels per second, `MOVING_PLATFORM_SPEED` if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    /// How long the platform waits at each waypoint, `MOVING_PLATFORM_WAIT` if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<f32>,
+}
+
+/// A platform of the level file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatformSpec {
+    pub rect: Area,
+    pub solidity: Solidity,
+    /// The path of a moving platform, or `None` for a platform that stays put.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathSpec>,
+    /// Whether the platform is only drawn by the tiles over it, as the ground of a Tiled map.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+/// A tile of the world tileset drawn over `rect`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TileSpec {
+    pub rect: Area,
+    /// The index of the tile in the tileset, row by row.
+    pub tile: u32,
+}
+
+/// A slope of the level file: a right triangle standing on the bottom of `rect`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SlopeSpec {
+    pub rect: Area,
+    pub rises_right: bool,
+}
+
+/// A level as written in a level file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelFile {
+    /// The width and height of the level, in pixels.
+    pub size: (f32, f32),
+    pub boundaries: Boundaries,
+    /// Where the player spawns.
+    pub player: (f32, f32),
+    /// Where the exit door stands.
+    pub exit: (f32, f32),
+    #[serde(default)]
+    pub platforms: Vec<PlatformSpec>,
+    #[serde(default)]
+    pub slopes: Vec<SlopeSpec>,
+    #[serde(default)]
+    pub checkpoints: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub keys: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub blocks: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub items: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub baddies: Vec<(f32, f32)>,
+    /// The tiles drawn from the world tileset, in drawing order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiles: Vec<TileSpec>,
+}
+
+impl LevelFile {
+    /// Loads a level from `path`: an ASCII grid if it is a `.txt` file, a Tiled map if it is
+    /// a `.tmj` or `.json` file, a RON level file otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        if extension == Some("tmx") {
+            return Err(ConfigError {
+                line: None,
+                message: "TMX maps are not supported: export the map from Tiled as JSON".to_owned(),
+            });
+        }
+        let contents = fs::read_to_string(path).map_err(|err| ConfigError {
+            line: None,
+            message: format!("cannot read {}: {}", path.display(), err),
+        })?;
+        match extension {
+            Some("txt") => Self::from_grid(&contents),
+            Some("tmj" | "json") => tiled::import(&contents),
+            _ => Self::parse(&contents),
+        }
+    }
+
+    /// Parses a level from the contents of a level file. Optional values, such as the path of a
+    /// moving platform, can be written without `Some(...)`.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let level: Self = ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(contents)
+            .map_err(|err| ConfigError {
+                line: Some(err.position.line),
+                message: err.code.to_string(),
+            })?;
+        if level.size.0 <= 0. || level.size.1 <= 0. {
+            return Err(ConfigError {
+                line: None,
+                message: format!(
+                    "invalid level size {} x {}: it must be positive",
+                    level.size.0, level.size.1
+                ),
+            });
+        }
+        Ok(level)
+    }
+
+    /// Saves the level to a level file at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, self.to_file_string())
+    }
+
+    /// Formats the level as the contents of a level file, which `parse` reads back.
+    pub fn to_file_string(&self) -> String {
+        let config = PrettyConfig::new().extensions(Extensions::IMPLICIT_SOME);
+        ron::ser::to_string_pretty(self, config).expect("a level always converts to RON")
+    }
+
+    /// Converts an ASCII grid into a level, each character covering one cell:
+    /// - `#`: solid ground,
+    /// - `=`: a one-way platform along the top of the cell,
+    /// - `B`, `I`, `K`, `E`: a block, an item, a key or a baddie,
+    /// - `P`: the player's spawn point and `D`: the exit door, exactly one of each,
+    /// - a space: nothing.
+    ///
+    /// The level is as large as the grid, whose edges are its boundaries. Blank lines before and
+    /// after the grid are ignored. Side by side `#` and `=` cells make a single platform, and
+    /// what spawns in a cell stands at the middle of its bottom.
+    pub fn from_grid(grid: &str) -> Result<Self, ConfigError> {
+        let lines: Vec<(usize, &str)> = grid
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim_end()))
+            .skip_while(|(_, line)| line.is_empty())
+            .collect();
+        let rows = lines
+            .iter()
+            .rposition(|(_, line)| !line.is_empty())
+            .map_or(&[][..], |last| &lines[..=last]);
+        let columns = rows
+            .iter()
+            .map(|(_, line)| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let cell = LEVEL_GRID_CELL_SIZE;
+
+        let mut level = Self {
+            size: (columns as f32 * cell, rows.len() as f32 * cell),
+            boundaries: Boundaries {
+                ground: 0.,
+                ceiling: 0.,
+                walls: 0.,
+            },
+            player: (0., 0.),
+            exit: (0., 0.),
+            platforms: Vec::new(),
+            slopes: Vec::new(),
+            checkpoints: Vec::new(),
+            keys: Vec::new(),
+            blocks: Vec::new(),
+            items: Vec::new(),
+            baddies: Vec::new(),
+            tiles: Vec::new(),
+        };
+        let mut player = None;
+        let mut exit = None;
+        for (row, &(line_number, line)) in rows.iter().enumerate() {
+            let error = |message: String| ConfigError {
+                line: Some(line_number),
+                message,
+            };
+            let top = row as f32 * cell;
+            // The platform being extended along the row, with its solidity.
+            let mut run: Option<(Solidity, Rect)> = None;
+            for (column, c) in line.chars().chain([' ']).enumerate() {
+                let left = column as f32 * cell;
+                // What spawns in the cell stands at the middle of its bottom.
+                let spawn = |size: f32| (left + (cell - size) / 2., top + cell - size);
+                let solidity = match c {
+                    '#' => Some(Solidity::Solid),
+                    '=' => Some(Solidity::OneWay),
+                    _ => None,
+                };
+                match (&mut run, solidity) {
+                    (Some((current, rect)), Some(solidity)) if *current == solidity => {
+                        rect.w += cell;
+                    }
+                    _ => {
+                        if let Some((solidity, rect)) = run.take() {
+                            level.platforms.push(PlatformSpec {
+                                rect: Area(rect),
+                                solidity,
+                                path: None,
+                                hidden: false,
+                            });
+                        }
+                        run = solidity.map(|solidity| {
+                            let height = match solidity {
+                                Solidity::Solid => cell,
+                                Solidity::OneWay => LEVEL_GRID_PLATFORM_HEIGHT,
+                            };
+                            (solidity, Rect::new(left, top, cell, height))
+                        });
+                    }
+                }
+
+                match c {
+                    '#' | '=' | ' ' => {}
+                    'B' => level.blocks.push(spawn(BLOCK_SIZE)),
+                    'I' => level.items.push(spawn(ITEM_SIZE)),
+                    'K' => level.keys.push(spawn(KEY_SIZE)),
+                    'E' => level.baddies.push(spawn(BADDIE_SIZE)),
+                    'P' if player.is_some() => {
+                        return Err(error("a second player spawn `P`".to_owned()));
+                    }
+                    'P' => player = Some(spawn(PLAYER_SIZE)),
+                    'D' if exit.is_some() => {
+                        return Err(error("a second door `D`".to_owned()));
+                    }
+                    'D' => exit = Some((left + cell / 2., top + cell)),
+                    _ => {
+                        return Err(error(format!(
+                            "unknown character `{}` in column {}",
+                            c,
+                            column + 1
+                        )));
+                    }
+                }
+            }
+        }
+
+        let missing = |what: &str| ConfigError {
+            line: None,
+            message: format!("missing {}", what),
+        };
+        level.player = player.ok_or_else(|| missing("player spawn `P`"))?;
+        level.exit = exit.ok_or_else(|| missing("door `D`"))?;
+        Ok(level)
+    }
+}
+
+/// Returns the position of a point of a level file.
+pub fn point((x, y): (f32, f32)) -> Vec2 {
+    vec2(x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::CAMPAIGN;
+
+    const TINY: &str = "LevelFile(
+    size: (400, 300),
+    boundaries: (ground: 20, ceiling: 20, walls: 20),
+    player: (40, 230),
+    exit: (350, 280),
+    platforms: [
+        (rect: (100, 200, 80, 10), solidity: OneWay),
+        (rect: (200, 150, 80, 10), solidity: Solid, path: (mode: PingPong, to: [(250, 150)])),
+    ],
+    keys: [(120, 170)],
+)";
+
+    #[test]
+    fn level_files_parse_with_defaults_for_what_they_leave_out() {
+        let level = LevelFile::parse(TINY).unwrap();
+        assert_eq!(level.size, (400., 300.));
+        assert_eq!(
+            level.platforms[0].rect,
+            Area(Rect::new(100., 200., 80., 10.))
+        );
+        let path = level.platforms[1].path.as_ref().unwrap();
+        assert_eq!(path.mode, PathMode::PingPong);
+        assert_eq!(path.speed, None);
+        assert!(level.blocks.is_empty() && level.baddies.is_empty());
+
+        for campaign_level in CAMPAIGN {
+            assert!(
+                LevelFile::parse(campaign_level.source).is_ok(),
+                "{}",
+                campaign_level.name
+            );
+        }
+
+        // Saved levels read back the same.
+        let saved = LevelFile::parse(&level.to_file_string()).unwrap();
+        assert_eq!(saved, level);
+        let meadow = CAMPAIGN[0].level_file();
+        assert_eq!(LevelFile::parse(&meadow.to_file_string()).unwrap(), meadow);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let err =
+            LevelFile::parse(&TINY.replace("solidity: Solid,", "solidity: Bouncy,")).unwrap_err();
+        assert_eq!(err.line, Some(8));
+
+        let err =
+            LevelFile::parse(&TINY.replace("(100, 200, 80, 10)", "(100, 200, 0, 10)")).unwrap_err();
+        assert_eq!(err.line, Some(7));
+        assert!(
+            err.message.contains("invalid size 0 x 10"),
+            "{}",
+            err.message
+        );
+
+        let err = LevelFile::parse(&TINY.replace("    exit: (350, 280),\n", "")).unwrap_err();
+        assert!(err.message.contains("exit"), "{}", err.message);
+    }
+
+    #[test]
+    fn ascii_grids_become_levels_one_cell_per_character() {
+        let grid = "
+#######
+#K   D#
+# ==  #
+#P IBE#
+#######
+";
+        let level = LevelFile::from_grid(grid).unwrap();
+        let cell = LEVEL_GRID_CELL_SIZE;
+        assert_eq!(level.size, (7. * cell, 5. * cell));
+        // Side by side cells make a single platform.
+        assert_eq!(
+            level.platforms[0].rect,
+            Area(Rect::new(0., 0., 7. * cell, cell))
+        );
+        let one_way = level
+            .platforms
+            .iter()
+            .find(|platform| platform.solidity == Solidity::OneWay)
+            .unwrap();
+        assert_eq!(
+            one_way.rect,
+            Area(Rect::new(
+                2. * cell,
+                2. * cell,
+                2. * cell,
+                LEVEL_GRID_PLATFORM_HEIGHT
+            ))
+        );
+        // What spawns in a cell stands at the middle of its bottom.
+        assert_eq!(
+            level.player,
+            (cell + (cell - PLAYER_SIZE) / 2., 4. * cell - PLAYER_SIZE)
+        );
+        assert_eq!(level.exit, (5.5 * cell, 2. * cell));
+        assert_eq!(level.blocks, vec![(4. * cell, 3. * cell)]);
+        assert_eq!(
+            (level.items.len(), level.keys.len(), level.baddies.len()),
+            (1, 1, 1)
+        );
+
+        let err = LevelFile::from_grid("\n#P D#\n#?  #\n").unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.message, "unknown character `?` in column 2");
+        let err = LevelFile::from_grid("P P D").unwrap_err();
+        assert_eq!(err.message, "a second player spawn `P`");
+        let err = LevelFile::from_grid("P  ").unwrap_err();
+        assert_eq!(err.message, "missing door `D`");
+
+        let sketch = LevelFile::load("assets/levels/arena.txt").unwrap();
+        assert_eq!((sketch.keys.len(), sketch.baddies.len()), (2, 3));
+    }
+}
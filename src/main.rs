@@ -2,18 +2,33 @@
 //!
 //! This is the entry point of the platformer game.
 
+mod arena;
 mod baddies;
 mod blocks;
+mod body;
+mod broadphase;
 mod camera;
+mod campaign;
 mod constants;
+mod editor;
 mod game;
 mod game_states;
+mod gamepad;
 mod gui;
+mod highscores;
+mod input;
 mod items;
 mod keys;
 mod level;
+mod level_file;
 mod physics;
+mod platforms;
 mod player;
+mod replay;
+mod score;
+mod tiled;
+mod timestep;
+mod world;
 
 /// Configures the game window.
 fn window_conf() -> macroquad::prelude::Conf {
@@ -24,8 +39,72 @@ fn window_conf() -> macroquad::prelude::Conf {
     }
 }
 
+/// Reads the command-line options:
+/// - `--seed <number>`: generates every run from this seed.
+/// - `--record <file>`: saves a replay of each run to this file when it ends.
+/// - `--replay <file>`: plays back a replay instead of reading the player's input.
+/// - `--level <file>`: plays this level file, ASCII grid if it is a `.txt` file or Tiled map
+///   if it is a `.tmj` or `.json` file, instead of the campaign. The level editor saves it
+///   back as a level file next to it.
+fn options_from_args(args: Vec<String>) -> game::Options {
+    let mut options = game::Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().unwrap_or_default();
+                match value.parse() {
+                    Ok(seed) => options.seed = Some(seed),
+                    Err(_) => eprintln!("Ignoring invalid seed '{}', expected a number", value),
+                }
+            }
+            "--record" => options.record_path = args.next().map(Into::into),
+            "--replay" => {
+                let path = args.next().unwrap_or_default();
+                match replay::Replay::load(&path) {
+                    Ok(replay) => options.replay = Some(replay),
+                    Err(err) => eprintln!("Ignoring replay '{}': {}", path, err),
+                }
+            }
+            "--level" => {
+                let path = args.next().unwrap_or_default();
+                match level_file::LevelFile::load(&path) {
+                    Ok(level) => {
+                        options.level = Some(level);
+                        options.level_path = Some(path.into());
+                    }
+                    Err(err) => eprintln!("Ignoring level '{}': {}", path, err),
+                }
+            }
+            _ => eprintln!("Ignoring unknown argument '{}'", arg),
+        }
+    }
+    options
+}
+
+/// Plays a replay file back without opening a window, for use in CI.
+/// Returns the process exit code: 0 when the replay plays back without desync.
+fn verify_replay(path: &str) -> i32 {
+    match replay::Replay::load(path).and_then(|replay| replay.verify()) {
+        Ok(_) => {
+            println!("{}: OK", path);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            1
+        }
+    }
+}
+
 /// The entry point of the application.
-#[macroquad::main(window_conf)]
-async fn main() {
-    game::run().await;
+/// `--verify-replay <file>` checks a replay headlessly; otherwise the game window is opened.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, path] = args.as_slice()
+        && flag == "--verify-replay"
+    {
+        std::process::exit(verify_replay(path));
+    }
+    macroquad::Window::from_config(window_conf(), game::run(options_from_args(args)));
 }
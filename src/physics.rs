@@ -1,408 +1,934 @@
 //! Physics Module
 //!
-//! This module handles collision detection and resolution.
+//! This module handles collision detection and resolution. Every body, whatever its kind, goes
+//! through the same pipeline, `move_body`: it is kept inside the level, landed on (or bounced
+//! off) surfaces and stopped against blocks, as tuned by its `BodyOptions`. The pipeline then
+//! reports what the body touched as `Contacts`, so that each kind of object can react in its
+//! own way (a baddie turning around at a wall, an item coming to rest).
 
-use crate::baddies::{Baddie, BaddieState};
+use crate::arena::Handle;
 use crate::blocks::{Block, BlockState};
-use crate::constants::{
-    BADDIE_GRAB_CHANCE, BADDIE_GRAB_ITEM_CHANCE, BADDIE_MAX_GRAB_DURATION,
-    BADDIE_MAX_ITEM_HOLD_DURATION, BADDIE_MIN_GRAB_DURATION, BADDIE_MIN_ITEM_HOLD_DURATION,
-    ITEM_BOUNCE_ENERGY_LOSS, ITEM_MIN_BOUNCE_SPEED,
-};
-use crate::items::{Item, ItemState};
-use crate::player::{HeldObject, Player};
-use ::rand::{rng, Rng};
-use macroquad::prelude::{get_frame_time, vec2, Rect, Vec2};
-
-/// Resolves collisions between the player and the level, including boundaries, platforms, and blocks.
-pub fn resolve_player_collisions(
-    player: &mut Player,
-    platforms: &[Rect],
-    items: &[Item],
-    blocks: &[Block],
-    ground: &Rect,
-    left_wall: &Rect,
-    right_wall: &Rect,
-    ceiling: &Rect,
-) {
-    player.on_ground = false;
-
-    // Determine the width of the held object, if any, to adjust the player's bounding box.
-    // This prevents the player from moving into walls while holding an object.
-    let held_object_width = match player.held_object {
-        Some(HeldObject::Item(idx)) => items.get(idx).map_or(0.0, |item| item.size.x),
-        Some(HeldObject::Block(idx)) => blocks.get(idx).map_or(0.0, |block| block.size.x),
-        None => 0.0,
-    };
+use crate::body::{Body, BodyOptions};
+use crate::broadphase::SpatialGrid;
+use crate::constants::{GRID_CELL_SIZE, SLOPE_SNAP_DISTANCE};
+use crate::level::{Level, Slope};
+use macroquad::prelude::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A side of a body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// How a surface stops bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Solidity {
+    /// Bodies are stopped on all four faces: they land on it, bump their head under it and are
+    /// pushed back from its sides.
+    Solid,
+    /// Bodies only land on it from above, and pass through it from below and from the sides.
+    OneWay,
+}
+
+/// What a body touched while being moved by `move_body`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Contacts {
+    /// The side of the body that was pushed back from a level wall or a solid platform's side.
+    pub wall: Option<Side>,
+    /// Whether the body bumped its head on the ceiling or under a solid surface.
+    pub ceiling: bool,
+    /// Whether the body landed on a surface, whether it came to rest or bounced off it.
+    pub landed: bool,
+    /// The block the body ran into sideways, and the side of the body that hit it.
+    pub block: Option<(Handle<Block>, Side)>,
+}
+
+/// How far an object carried by a body sticks out in front of it. The carried object is kept
+/// inside the level along with the body.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Carry {
+    pub width: f32,
+    pub facing_right: bool,
+}
+
+/// A surface in the broadphase grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Solid {
+    Ground,
+    Platform(usize, Solidity),
+    /// A slope, stored by its bounding box. Slopes are not swept against: bodies are put back
+    /// on them once they have moved.
+    Slope(usize),
+    Block(Handle<Block>),
+}
 
-    // --- Player vs. Level Bounds ---
-    // Adjust player position to prevent moving beyond level boundaries.
-    if player.facing_right {
-        let player_right_edge = player.position.x + player.size.x + held_object_width;
-        if player_right_edge > right_wall.left() {
-            player.position.x = right_wall.left() - player.size.x - held_object_width;
+impl Solid {
+    /// Returns the faces of the surface that stop bodies.
+    fn faces(self) -> &'static [Face] {
+        match self {
+            Solid::Ground | Solid::Platform(_, Solidity::OneWay) => &[Face::Top],
+            Solid::Platform(_, Solidity::Solid) | Solid::Block(_) => {
+                &[Face::Top, Face::Bottom, Face::Left, Face::Right]
+            }
+            Solid::Slope(_) => &[],
         }
-    } else {
-        let player_left_edge = player.position.x - held_object_width;
-        if player_left_edge < left_wall.right() {
-            player.position.x = left_wall.right() + held_object_width;
+    }
+
+    /// Returns the block this surface is, if it is one.
+    fn block(self) -> Option<Handle<Block>> {
+        match self {
+            Solid::Block(handle) => Some(handle),
+            _ => None,
         }
     }
+}
 
-    // Prevent player from moving through the ceiling.
-    if player.rect().overlaps(ceiling) {
-        player.position.y = ceiling.bottom();
-        player.velocity.y = 0.;
+/// The level geometry and the solid blocks that bodies collide with. Surfaces are kept in a
+/// `SpatialGrid`, so that a body is only tested against the surfaces around it.
+pub struct Colliders {
+    ground: Rect,
+    ceiling: Rect,
+    left_wall: Rect,
+    right_wall: Rect,
+    slopes: Vec<Slope>,
+    grid: SpatialGrid<Solid>,
+    /// Where each solid block was inserted in the grid, to remove it again.
+    blocks: HashMap<Handle<Block>, Rect>,
+}
+
+impl Colliders {
+    /// Gathers the level's bounds, its platforms and its idle blocks.
+    pub fn new(level: &Level) -> Self {
+        let mut colliders = Self {
+            ground: level.ground,
+            ceiling: level.ceiling,
+            left_wall: level.left_wall,
+            right_wall: level.right_wall,
+            slopes: Vec::new(),
+            grid: SpatialGrid::new(GRID_CELL_SIZE),
+            blocks: HashMap::new(),
+        };
+        colliders.rebuild(level);
+        colliders
     }
 
-    // --- Player vs. Surfaces (Ground, Platforms, Blocks) ---
-    // Create a unified list of all solid surfaces the player can land on.
-    let mut surfaces = platforms.to_vec();
-    surfaces.push(*ground);
-    for block in blocks.iter() {
-        if block.state == BlockState::Idle {
-            surfaces.push(block.rect());
+    /// Gathers the level's surfaces again, once the blocks have moved.
+    pub fn rebuild(&mut self, level: &Level) {
+        self.ground = level.ground;
+        self.ceiling = level.ceiling;
+        self.left_wall = level.left_wall;
+        self.right_wall = level.right_wall;
+        self.grid.clear();
+        self.blocks.clear();
+
+        self.grid.insert(Solid::Ground, level.ground);
+        for (i, platform) in level.platforms.iter().enumerate() {
+            self.grid
+                .insert(Solid::Platform(i, platform.solidity), platform.rect);
+        }
+        self.slopes.clear();
+        self.slopes.extend_from_slice(&level.slopes);
+        for (i, slope) in level.slopes.iter().enumerate() {
+            self.grid.insert(Solid::Slope(i), slope.rect);
+        }
+        for (handle, block) in level.blocks.iter() {
+            if block.state == BlockState::Idle {
+                self.insert_block(handle, block.body.rect());
+            }
         }
     }
 
-    // Check for vertical collisions.
-    if player.velocity.y >= 0. {
-        for surface in &surfaces {
-            if player.rect().overlaps(surface) {
-                // To prevent sinking, check if the player was above the surface in the previous frame.
-                let previous_player_bottom =
-                    player.position.y + player.size.y - player.velocity.y * get_frame_time();
-                if previous_player_bottom <= surface.y {
-                    player.position.y = surface.y - player.size.y;
-                    player.velocity.y = 0.;
-                    player.on_ground = true;
-                }
-            }
+    /// Moves a platform of the level to where it now stands.
+    pub fn move_platform(&mut self, index: usize, solidity: Solidity, from: Rect, to: Rect) {
+        self.grid.remove(Solid::Platform(index, solidity), from);
+        self.grid.insert(Solid::Platform(index, solidity), to);
+    }
+
+    /// Stops treating a block as solid, for instance once it is carried.
+    pub fn remove_block(&mut self, handle: Handle<Block>) {
+        if let Some(rect) = self.blocks.remove(&handle) {
+            self.grid.remove(Solid::Block(handle), rect);
         }
     }
 
-    // --- Player vs. Blocks (Side Collisions) ---
-    // Handle horizontal collisions with blocks separately to prevent pushing.
-    for block in blocks.iter() {
-        if block.state == BlockState::Idle {
-            let player_rect = player.rect();
-            let block_rect = block.rect();
-            if player_rect.overlaps(&block_rect) {
-                let previous_player_right =
-                    player.position.x + player.size.x - player.velocity.x * get_frame_time();
-                let previous_player_left = player.position.x - player.velocity.x * get_frame_time();
-
-                // Collision from the left.
-                if previous_player_right <= block_rect.left()
-                    && player_rect.right() > block_rect.left()
-                {
-                    player.position.x = block_rect.left() - player.size.x;
-                }
-                // Collision from the right.
-                else if previous_player_left >= block_rect.right()
-                    && player_rect.left() < block_rect.right()
+    /// Treats a block as solid where it now stands.
+    pub fn insert_block(&mut self, handle: Handle<Block>, rect: Rect) {
+        self.remove_block(handle);
+        self.grid.insert(Solid::Block(handle), rect);
+        self.blocks.insert(handle, rect);
+    }
+
+    /// Returns the surfaces overlapping `area` that a body can stand on.
+    fn surfaces(&self, area: Rect) -> impl Iterator<Item = Rect> + '_ {
+        self.grid.query(area).map(|(_, rect)| rect)
+    }
+
+    /// Returns the one-way platform a body with bounds `rect` is standing on, if any.
+    pub fn one_way_platform_under(&self, rect: &Rect) -> Option<usize> {
+        let feet = Rect::new(rect.x, rect.bottom(), rect.w, 0.);
+        self.grid
+            .query(feet)
+            .find_map(|(solid, surface)| match solid {
+                Solid::Platform(i, Solidity::OneWay)
+                    if (surface.top() - rect.bottom()).abs() <= SWEEP_TOLERANCE
+                        && rect.right() > surface.left()
+                        && rect.left() < surface.right() =>
                 {
-                    player.position.x = block_rect.right();
+                    Some(i)
                 }
+                _ => None,
+            })
+    }
+
+    /// Returns whether a point lies on something a body can stand on. Any point over a slope
+    /// counts, as the surface drops away under the front of a character walking downhill.
+    pub fn supports(&self, point: Vec2) -> bool {
+        self.surfaces(Rect::new(point.x, point.y, 0., 0.))
+            .any(|surface| surface.contains(point))
+    }
+}
+
+/// Returns the speed of a body rebounding off a surface it hit at `speed`.
+fn rebound(speed: f32, bounce: f32) -> f32 {
+    -speed * bounce
+}
+
+/// How far, in pixels, a body may already overlap a surface and still be stopped by it. This
+/// absorbs rounding errors for bodies resting exactly on a surface.
+const SWEEP_TOLERANCE: f32 = 0.01;
+
+/// The number of surfaces a body can hit, and slide or bounce off, during a single step.
+const MAX_SWEEP_ITERATIONS: usize = 4;
+
+/// A face of a surface that stops bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Face {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Returns when, as a fraction of `motion`, a body with bounds `rect` moving by `motion` first
+/// touches the given face of `surface`, if it does.
+fn time_of_impact(rect: &Rect, motion: Vec2, surface: &Rect, face: Face) -> Option<f32> {
+    let (distance, speed) = match face {
+        Face::Top => (surface.top() - rect.bottom(), motion.y),
+        Face::Bottom => (rect.top() - surface.bottom(), -motion.y),
+        Face::Left => (surface.left() - rect.right(), motion.x),
+        Face::Right => (rect.left() - surface.right(), -motion.x),
+    };
+    if speed <= 0. || distance < -SWEEP_TOLERANCE || distance > speed {
+        return None;
+    }
+
+    let time = (distance / speed).max(0.);
+    let at = rect.offset(motion * time);
+    let touches = match face {
+        Face::Top | Face::Bottom => at.right() > surface.left() && at.left() < surface.right(),
+        Face::Left | Face::Right => at.bottom() > surface.top() && at.top() < surface.bottom(),
+    };
+    touches.then_some(time)
+}
+
+/// Returns whether `rect` overlaps `surface` by more than the rounding tolerance on both axes.
+fn embedded(rect: &Rect, surface: &Rect) -> bool {
+    rect.right() - surface.left() > SWEEP_TOLERANCE
+        && surface.right() - rect.left() > SWEEP_TOLERANCE
+        && rect.bottom() - surface.top() > SWEEP_TOLERANCE
+        && surface.bottom() - rect.top() > SWEEP_TOLERANCE
+}
+
+/// Pushes a body that ended up inside a fully solid surface (for instance a block dropped onto
+/// it) out through the nearest face.
+fn push_out_of_solids(body: &mut Body, colliders: &Colliders, contacts: &mut Contacts) {
+    let rect = body.rect();
+    for (solid, surface) in colliders.grid.query(rect) {
+        if solid.faces().len() < 4 {
+            continue;
+        }
+
+        let rect = body.rect();
+        let pushes = [
+            (surface.top() - rect.bottom(), Face::Top),
+            (surface.bottom() - rect.top(), Face::Bottom),
+            (surface.left() - rect.right(), Face::Left),
+            (surface.right() - rect.left(), Face::Right),
+        ];
+        // A body standing on the floor is not pushed down through it.
+        let Some((push, face)) = pushes
+            .into_iter()
+            .filter(|&(_, face)| !(body.on_ground && face == Face::Bottom))
+            .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+        else {
+            continue;
+        };
+        // Only a real overlap on both axes needs resolving.
+        if !embedded(&rect, &surface) {
+            continue;
+        }
+
+        match face {
+            Face::Top => {
+                body.position.y += push;
+                body.velocity.y = body.velocity.y.min(0.);
+                body.on_ground = true;
+                body.resting_on = solid.block();
+                contacts.landed = true;
+            }
+            Face::Bottom => {
+                body.position.y += push;
+                body.velocity.y = body.velocity.y.max(0.);
+                contacts.ceiling = true;
+            }
+            Face::Left | Face::Right => {
+                body.position.x += push;
+                body.velocity.x = 0.;
             }
         }
     }
 }
 
-/// Resolves collisions for a single baddie with the level, including boundaries, platforms, and blocks.
-pub fn resolve_baddie_collisions(
-    baddie: &mut Baddie,
-    platforms: &[Rect],
-    blocks: &mut [Block],
-    items: &mut [Item],
-    ground: &Rect,
-    left_wall: &Rect,
-    right_wall: &Rect,
-    ceiling: &Rect,
+/// Puts a body that moved from `start` onto the slopes it crossed from above. A character that
+/// was standing and walks downhill is also pulled down onto the slope, so that it does not
+/// leave it in a series of small falls.
+///
+/// Characters stand on slopes. Other bodies bounce off them or slide down them, and only come
+/// to rest once they reach flat ground.
+fn land_on_slopes(
+    body: &mut Body,
+    options: &BodyOptions,
+    start: Rect,
+    was_on_ground: bool,
+    colliders: &Colliders,
+    contacts: &mut Contacts,
 ) {
-    baddie.on_ground = false;
-
-    let held_block_width = if let Some(block_id) = baddie.grabbed_block_id {
-        blocks.get(block_id).map_or(0.0, |b| b.size.x)
-    } else {
-        0.0
+    let rect = body.rect();
+    let area = rect
+        .combine_with(start)
+        .combine_with(rect.offset(Vec2::new(0., SLOPE_SNAP_DISTANCE)));
+    // A body over several slopes rests on the highest one.
+    let landing = colliders
+        .grid
+        .query(area)
+        .filter_map(|(solid, _)| match solid {
+            Solid::Slope(i) => Some(&colliders.slopes[i]),
+            _ => None,
+        })
+        // A body that just stepped off the end of a slope is still put on that end.
+        .filter(|slope| {
+            let swept = rect.combine_with(start);
+            swept.right() > slope.rect.left() && swept.left() < slope.rect.right()
+        })
+        .filter_map(|slope| {
+            let surface = slope.surface_under(&rect);
+            let was_above = start.bottom() <= slope.surface_under(&start) + SWEEP_TOLERANCE;
+            let crossed = rect.bottom() >= surface - SWEEP_TOLERANCE;
+            let snapped = was_on_ground
+                && body.velocity.y >= 0.
+                && surface - rect.bottom() <= SLOPE_SNAP_DISTANCE;
+            (was_above && (crossed || snapped)).then_some((surface, slope))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0));
+
+    let Some((surface, slope)) = landing else {
+        return;
     };
+    // A sliding body that reached flat ground at the foot of the slope rests there.
+    if options.slides && body.on_ground {
+        return;
+    }
+    body.position.y = surface - body.size.y;
+    body.resting_on = None;
+    contacts.landed = true;
+
+    if !options.slides {
+        body.velocity.y = body.velocity.y.min(0.);
+        body.on_ground = true;
+        return;
+    }
 
-    let held_item_width = if let Some(item_id) = baddie.held_item_id {
-        items.get(item_id).map_or(0.0, |i| i.size.x)
-    } else {
-        0.0
-    };
+    // The body is not at rest until it reaches flat ground.
+    body.on_ground = false;
+    // The surface normal, pointing up.
+    let normal = Vec2::new(slope.gradient(), -1.).normalize();
+    let into_surface = body.velocity.dot(normal);
+    if into_surface < 0. {
+        let bounces = options.bounce > 0. && body.velocity.length() > options.min_bounce_speed;
+        let bounce = if bounces { options.bounce } else { 0. };
+        body.velocity -= normal * into_surface * (1. + bounce);
+    }
+}
 
-    let held_object_width = held_block_width + held_item_width;
+/// Returns whether a body is standing on top of `surface`.
+pub fn stands_on(body: &Body, surface: &Rect) -> bool {
+    let rect = body.rect();
+    body.on_ground
+        && (rect.bottom() - surface.top()).abs() <= SWEEP_TOLERANCE
+        && rect.right() > surface.left()
+        && rect.left() < surface.right()
+}
 
-    // --- Baddie vs. Walls ---
-    // Reverse direction upon hitting a wall.
-    if baddie.facing_right {
-        if baddie.rect().right() + held_object_width > right_wall.left() {
-            baddie.position.x = right_wall.left() - baddie.size.x - held_object_width;
-            baddie.change_direction();
+/// Moves a body riding a moving platform along with it, by `motion` during a step of `dt`
+/// seconds. The level bounds and fully solid surfaces in the way hold the body back
+/// horizontally, while the platform slides on under it. Returns `false`, leaving the body where
+/// it was, when there is no room left for it: the move would crush it against the ceiling or a
+/// fully solid surface.
+pub fn carry_rider(body: &mut Body, motion: Vec2, dt: f32, colliders: &Colliders) -> bool {
+    let rect = body.rect();
+    let moved = rect.offset(Vec2::new(motion.x, 0.));
+    let mut x = moved.x.clamp(
+        colliders.left_wall.right().min(rect.x),
+        (colliders.right_wall.left() - rect.w).max(rect.x),
+    );
+    for (solid, surface) in colliders.grid.query(moved) {
+        if solid.faces().len() < 4 || !embedded(&moved, &surface) {
+            continue;
         }
-    } else {
-        if baddie.rect().left() - held_object_width < left_wall.right() {
-            baddie.position.x = left_wall.right() + held_object_width;
-            baddie.change_direction();
+        if motion.x > 0. && surface.left() >= rect.right() - SWEEP_TOLERANCE {
+            x = x.min(surface.left() - rect.w);
+        } else if motion.x < 0. && surface.right() <= rect.left() + SWEEP_TOLERANCE {
+            x = x.max(surface.right());
         }
     }
 
-    // --- Baddie vs. Ceiling ---
-    if baddie.state == BaddieState::Elevation && baddie.rect().overlaps(ceiling) {
-        baddie.state = BaddieState::Idle;
-        baddie.velocity.y = 0.;
-        baddie.position.y = ceiling.bottom();
-        baddie.drop_held_object();
-        return; // Exit early as no other collision checks are needed
+    let held = Rect::new(x, rect.y + motion.y, rect.w, rect.h);
+    let crushed = held.top() < colliders.ceiling.bottom()
+        || colliders
+            .grid
+            .query(held)
+            .any(|(solid, surface)| solid.faces().len() == 4 && embedded(&held, &surface));
+    if crushed {
+        return false;
     }
+    body.position = held.point();
+    body.platform_velocity = (held.point() - rect.point()) / dt;
+    true
+}
 
-    // --- Baddie vs. Surfaces (Ground, Platforms, Blocks) ---
-    if baddie.state != BaddieState::Elevation {
-        // Create a unified list of all solid surfaces the baddie can land on.
-        let mut surfaces = platforms.to_vec();
-        surfaces.push(*ground);
-        for block in blocks.iter() {
-            if block.state == BlockState::Idle {
-                surfaces.push(block.rect());
+/// Returns whether a body with bounds `rect` moving by `motion` overlaps `target` at any point
+/// of its motion, however fast it goes. Touching edges do not count as overlapping.
+pub fn sweep_overlaps(rect: &Rect, motion: Vec2, target: &Rect) -> bool {
+    // Shrink the body to a point and grow the target by the body's size, then clip the
+    // point's path against the grown target, one axis at a time.
+    let target = Rect::new(
+        target.x - rect.w,
+        target.y - rect.h,
+        target.w + rect.w,
+        target.h + rect.h,
+    );
+    let (mut enter, mut exit) = (0.0_f32, 1.0_f32);
+    for (start, delta, min, max) in [
+        (rect.x, motion.x, target.left(), target.right()),
+        (rect.y, motion.y, target.top(), target.bottom()),
+    ] {
+        if delta == 0. {
+            if start <= min || start >= max {
+                return false;
             }
+        } else {
+            let (t0, t1) = ((min - start) / delta, (max - start) / delta);
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
         }
+    }
+    enter < exit
+}
 
-        // Check for vertical collisions.
-        if baddie.velocity.y >= 0. {
-            for surface in &surfaces {
-                if baddie.rect().overlaps(surface) {
-                    let previous_baddie_bottom =
-                        baddie.position.y + baddie.size.y - baddie.velocity.y * get_frame_time();
-                    if previous_baddie_bottom <= surface.y {
-                        baddie.position.y = surface.y - baddie.size.y;
-                        baddie.velocity.y = 0.;
-                        baddie.on_ground = true;
-                    }
-                }
-            }
+/// Returns whether `solid` is the one-way platform a body is dropping through.
+fn is_dropped_through(body: &Body, solid: Solid) -> bool {
+    matches!(solid, Solid::Platform(i, Solidity::OneWay) if body.dropping_through == Some(i))
+}
+
+/// Moves a body from `from`, where it was before it moved this step, to where its velocity
+/// took it, stopping it at the first surface in its way, and reports what it touched.
+///
+/// The motion is swept: the time of impact with each surface along the path is computed, so a
+/// body cannot pass through a platform, a block or the level bounds however fast it moves or
+/// however long the step is. A body that lands or runs into a block carries on with the rest
+/// of its motion, sliding along or bouncing off the surface.
+pub fn move_body(
+    body: &mut Body,
+    from: Vec2,
+    options: &BodyOptions,
+    carry: Carry,
+    colliders: &Colliders,
+) -> Contacts {
+    let mut contacts = Contacts::default();
+    let was_on_ground = body.on_ground;
+    body.on_ground = false;
+    body.resting_on = None;
+
+    // Start again from where the body was before it moved this step.
+    let mut remaining = body.position - from;
+    body.position = from;
+    let start = body.rect();
+
+    // --- Body vs. Surfaces (Ground, Platforms, Blocks) ---
+    for _ in 0..MAX_SWEEP_ITERATIONS {
+        if remaining == Vec2::ZERO {
+            break;
         }
 
-        // --- Baddie vs. Blocks (Side Collisions) ---
-        // Handle horizontal collisions with blocks.
-        for (i, block) in blocks.iter_mut().enumerate() {
-            if let Some(grabbed_id) = baddie.grabbed_block_id {
-                if grabbed_id == i {
-                    continue;
+        let rect = body.rect();
+        let path = rect.combine_with(rect.offset(remaining));
+        // The earliest hit wins. On a tie, landing wins over hitting a side.
+        let hit = colliders
+            .grid
+            .query(path)
+            .filter(|(solid, _)| !is_dropped_through(body, *solid))
+            .flat_map(|(solid, surface)| {
+                solid.faces().iter().filter_map(move |&face| {
+                    time_of_impact(&rect, remaining, &surface, face)
+                        .map(|time| (time, face, solid, surface))
+                })
+            })
+            .min_by(|a, b| {
+                a.0.total_cmp(&b.0)
+                    .then((a.1 != Face::Top).cmp(&(b.1 != Face::Top)))
+            });
+
+        let Some((time, face, solid, surface)) = hit else {
+            body.position += remaining;
+            break;
+        };
+
+        body.position += remaining * time;
+        remaining *= 1. - time;
+
+        match face {
+            Face::Top => {
+                body.position.y = surface.top() - body.size.y;
+                if options.bounce > 0. && body.velocity.length() > options.min_bounce_speed {
+                    body.velocity.y = rebound(body.velocity.y, options.bounce);
+                    remaining.y = rebound(remaining.y, options.bounce);
+                } else {
+                    body.velocity.y = 0.;
+                    remaining.y = 0.;
+                    body.on_ground = true;
+                    body.resting_on = solid.block();
                 }
+                body.velocity.x *= 1. - options.friction;
+                remaining.x *= 1. - options.friction;
+                contacts.landed = true;
             }
-
-            if block.state == BlockState::Idle {
-                let baddie_rect = baddie.rect();
-                let block_rect = block.rect();
-                if baddie_rect.overlaps(&block_rect) {
-                    let previous_baddie_right =
-                        baddie.position.x + baddie.size.x - baddie.velocity.x * get_frame_time();
-                    let previous_baddie_left =
-                        baddie.position.x - baddie.velocity.x * get_frame_time();
-
-                    // Collision from the left.
-                    if previous_baddie_right <= block_rect.left()
-                        && baddie_rect.right() > block_rect.left()
-                    {
-                        baddie.position.x = block_rect.left() - baddie.size.x;
-                        if baddie.held_item_id.is_none()
-                            && baddie.grabbed_block_id.is_none()
-                            && rng().random_range(0.0..1.0) < BADDIE_GRAB_CHANCE
-                        {
-                            baddie.state = BaddieState::Grab;
-                            baddie.grabbed_block_id = Some(i);
-                            baddie.block_grab_timer = rng()
-                                .random_range(BADDIE_MIN_GRAB_DURATION..BADDIE_MAX_GRAB_DURATION);
-                            block.state = BlockState::Hooked;
-                        } else {
-                            baddie.change_direction();
-                        }
-                    }
-                    // Collision from the right.
-                    else if previous_baddie_left >= block_rect.right()
-                        && baddie_rect.left() < block_rect.right()
-                    {
-                        baddie.position.x = block_rect.right();
-                        if baddie.held_item_id.is_none()
-                            && baddie.grabbed_block_id.is_none()
-                            && rng().random_range(0.0..1.0) < BADDIE_GRAB_CHANCE
-                        {
-                            baddie.state = BaddieState::Grab;
-                            baddie.grabbed_block_id = Some(i);
-                            baddie.block_grab_timer = rng()
-                                .random_range(BADDIE_MIN_GRAB_DURATION..BADDIE_MAX_GRAB_DURATION);
-                            block.state = BlockState::Hooked;
-                        } else {
-                            baddie.change_direction();
-                        }
-                    }
-                }
+            Face::Bottom => {
+                body.position.y = surface.bottom();
+                body.velocity.y = rebound(body.velocity.y, options.bounce);
+                remaining.y = rebound(remaining.y, options.bounce);
+                contacts.ceiling = true;
             }
-        }
-
-        // --- Baddie vs. Items ---
-        if baddie.held_item_id.is_none() && baddie.grabbed_block_id.is_none() {
-            for (i, item) in items.iter_mut().enumerate() {
-                if item.state == ItemState::Idle
-                    && item.on_ground
-                    && baddie.rect().overlaps(&item.rect())
-                {
-                    if rng().random_range(0.0..1.0) < BADDIE_GRAB_ITEM_CHANCE {
-                        item.state = ItemState::Hooked;
-                        baddie.held_item_id = Some(i);
-                        baddie.item_hold_timer = rng().random_range(
-                            BADDIE_MIN_ITEM_HOLD_DURATION..BADDIE_MAX_ITEM_HOLD_DURATION,
-                        );
-                        break;
+            Face::Left | Face::Right => {
+                let side = if face == Face::Left {
+                    body.position.x = surface.left() - body.size.x;
+                    Side::Right
+                } else {
+                    body.position.x = surface.right();
+                    Side::Left
+                };
+                body.velocity.x = rebound(body.velocity.x, options.bounce);
+                remaining.x = rebound(remaining.x, options.bounce);
+                match solid {
+                    Solid::Block(handle) => contacts.block = Some((handle, side)),
+                    Solid::Ground | Solid::Platform(..) | Solid::Slope(_) => {
+                        contacts.wall = Some(side)
                     }
                 }
             }
         }
+    }
 
-        // --- Edge Detection ---
-        // Check if the baddie is about to fall off a platform or block.
-        if baddie.on_ground {
-            // Create a probe point just ahead of and below the baddie to check for ground.
-            let probe_x = if baddie.facing_right {
-                baddie.rect().right()
-            } else {
-                baddie.rect().left()
-            };
-            let probe_y = baddie.rect().bottom() + 1.0;
-            let probe_point = vec2(probe_x, probe_y);
-
-            let mut ground_ahead = false;
-            for surface in &surfaces {
-                if surface.contains(probe_point) {
-                    ground_ahead = true;
-                    break;
-                }
-            }
+    // A body dropping through a platform lands on it again once it has cleared it.
+    if body.dropping_through.is_some()
+        && !colliders
+            .grid
+            .query(body.rect())
+            .any(|(solid, _)| is_dropped_through(body, solid))
+    {
+        body.dropping_through = None;
+    }
 
-            // If there is no ground ahead, randomly decide whether to change direction or fall.
-            if !ground_ahead {
-                if rng().random_bool(0.1) {
-                    baddie.change_direction();
-                }
-            }
+    // --- Body vs. Slopes ---
+    land_on_slopes(
+        body,
+        options,
+        start,
+        was_on_ground,
+        colliders,
+        &mut contacts,
+    );
+
+    push_out_of_solids(body, colliders, &mut contacts);
+
+    // --- Body vs. Level Bounds ---
+    // The walls, the ceiling and the ground extend to the edges of the world, so anything past
+    // them is pushed back, however far it went. The carried object sticks out on the side the
+    // body is facing.
+    let (left_reach, right_reach) = if carry.facing_right {
+        (0.0, carry.width)
+    } else {
+        (carry.width, 0.0)
+    };
+    if body.position.x - left_reach < colliders.left_wall.right() {
+        body.position.x = colliders.left_wall.right() + left_reach;
+        if body.velocity.x < 0. {
+            body.velocity.x = rebound(body.velocity.x, options.bounce);
+        }
+        contacts.wall = Some(Side::Left);
+    } else if body.position.x + body.size.x + right_reach > colliders.right_wall.left() {
+        body.position.x = colliders.right_wall.left() - body.size.x - right_reach;
+        if body.velocity.x > 0. {
+            body.velocity.x = rebound(body.velocity.x, options.bounce);
+        }
+        contacts.wall = Some(Side::Right);
+    }
+
+    if body.position.y < colliders.ceiling.bottom() {
+        body.position.y = colliders.ceiling.bottom();
+        if body.velocity.y < 0. {
+            body.velocity.y = rebound(body.velocity.y, options.bounce);
         }
+        contacts.ceiling = true;
+    }
+
+    // Nothing ends up under the ground, even when pushed out of a block above it.
+    if body.rect().bottom() > colliders.ground.top() {
+        body.position.y = colliders.ground.top() - body.size.y;
+        body.velocity.y = body.velocity.y.min(0.);
+        body.on_ground = true;
+        body.resting_on = None;
+        contacts.landed = true;
     }
+
+    // A body that landed no longer moves with the platform it may have left.
+    if body.on_ground {
+        body.platform_velocity = Vec2::ZERO;
+    }
+
+    contacts
 }
 
-/// Resolves collisions for a single item with the level and blocks.
-pub fn resolve_item_collisions(
-    item: &mut Item,
-    platforms: &[Rect],
-    blocks: &[Block],
-    ground: &Rect,
-    left_wall: &Rect,
-    right_wall: &Rect,
-) {
-    item.on_ground = false;
-    let item_rect = item.rect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+    use crate::campaign::CAMPAIGN;
+    use crate::level::Platform;
+    use macroquad::prelude::vec2;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// Returns the colliders of a level without platforms or blocks.
+    fn empty_level() -> Colliders {
+        let mut level = Level::new(&CAMPAIGN[0].level_file());
+        level.platforms.clear();
+        level.slopes.clear();
+        level.blocks = Arena::new();
+        Colliders::new(&level)
+    }
+
+    #[test]
+    fn bodies_land_or_bounce_according_to_their_options() {
+        let colliders = empty_level();
+        let floor = colliders.ground.top();
+        let bouncy = BodyOptions {
+            bounce: 0.5,
+            min_bounce_speed: 10.0,
+            friction: 0.0,
+            holdable: false,
+            slides: true,
+        };
+
+        for (options, bounces) in [(BodyOptions::CHARACTER, false), (bouncy, true)] {
+            let mut body = Body::new(vec2(500., floor - 25.), vec2(20., 20.));
+            body.velocity = vec2(0., 600.);
+            let from = body.position;
+            body.advance(DT);
+            let contacts = move_body(&mut body, from, &options, Carry::default(), &colliders);
+            assert!(contacts.landed);
+            // A bouncing body rebounds for the rest of its motion.
+            assert_eq!(
+                body.rect().bottom(),
+                if bounces { floor - 2.5 } else { floor }
+            );
+            assert_eq!(body.on_ground, !bounces);
+            assert_eq!(body.velocity.y, if bounces { -300. } else { 0. });
+        }
+    }
 
-    // Item vs. Walls
-    if item_rect.overlaps(left_wall) {
-        item.position.x = left_wall.right();
-        item.velocity.x = -item.velocity.x;
+    #[test]
+    fn carried_objects_are_kept_inside_the_walls() {
+        let colliders = empty_level();
+        let mut body = Body::new(
+            vec2(colliders.right_wall.left() - 10., 500.),
+            vec2(20., 20.),
+        );
+        let carry = Carry {
+            width: 30.,
+            facing_right: true,
+        };
+        let from = body.position;
+        let contacts = move_body(&mut body, from, &BodyOptions::CHARACTER, carry, &colliders);
+        assert_eq!(contacts.wall, Some(Side::Right));
+        assert_eq!(
+            body.rect().right() + carry.width,
+            colliders.right_wall.left()
+        );
     }
-    if item_rect.overlaps(right_wall) {
-        item.position.x = right_wall.left() - item.size.x;
-        item.velocity.x = -item.velocity.x;
+
+    #[test]
+    fn running_into_a_block_reports_it() {
+        let mut colliders = empty_level();
+        let block = Arena::<Block>::new().insert(Block::new(vec2(500., 500.)));
+        colliders.insert_block(block, Rect::new(500., 500., 60., 60.));
+
+        let mut body = Body::new(vec2(475., 520.), vec2(20., 20.));
+        body.velocity = vec2(600., 0.);
+        body.advance(DT);
+        let contacts = move_body(
+            &mut body,
+            vec2(475., 520.),
+            &BodyOptions::CHARACTER,
+            Carry::default(),
+            &colliders,
+        );
+        assert_eq!(contacts.block, Some((block, Side::Right)));
+        assert_eq!(body.rect().right(), 500.);
+    }
+
+    #[test]
+    fn fast_bodies_do_not_tunnel_through_thin_surfaces() {
+        let mut level = Level::new(&CAMPAIGN[0].level_file());
+        level.platforms = vec![Platform::new(
+            Rect::new(400., 600., 200., 20.),
+            Solidity::OneWay,
+        )];
+        level.blocks = Arena::new();
+        let block = level.blocks.insert(Block::new(vec2(800., 300.)));
+        let colliders = Colliders::new(&level);
+
+        // Falling 5000px in a single step still lands on the 20px platform.
+        let mut body = Body::new(vec2(490., 500.), vec2(20., 20.));
+        body.velocity = vec2(0., 5000.);
+        let from = body.position;
+        body.advance(1.0);
+        let contacts = move_body(
+            &mut body,
+            from,
+            &BodyOptions::CHARACTER,
+            Carry::default(),
+            &colliders,
+        );
+        assert!(contacts.landed && body.on_ground);
+        assert_eq!(body.rect().bottom(), 600.);
+
+        // Crossing the level in a single step still stops at the block.
+        let mut body = Body::new(vec2(100., 320.), vec2(20., 20.));
+        body.velocity = vec2(3000., 0.);
+        let from = body.position;
+        body.advance(1.0);
+        let contacts = move_body(
+            &mut body,
+            from,
+            &BodyOptions::CHARACTER,
+            Carry::default(),
+            &colliders,
+        );
+        assert_eq!(contacts.block, Some((block, Side::Right)));
+        assert_eq!(body.rect().right(), 800.);
     }
 
-    // Combine all solid objects for collision detection
-    let mut colliders = platforms.to_vec();
-    colliders.push(*ground);
-    for block in blocks.iter() {
-        if block.state == BlockState::Idle {
-            colliders.push(block.rect());
+    #[test]
+    fn bodies_are_swept_from_where_they_started_the_step() {
+        let mut colliders = empty_level();
+        let block = Arena::<Block>::new().insert(Block::new(vec2(500., 500.)));
+        colliders.insert_block(block, Rect::new(500., 500., 60., 60.));
+
+        // Moved past the block by something other than its velocity, such as being carried.
+        let mut body = Body::new(vec2(580., 520.), vec2(20., 20.));
+        let contacts = move_body(
+            &mut body,
+            vec2(460., 520.),
+            &BodyOptions::CHARACTER,
+            Carry::default(),
+            &colliders,
+        );
+        assert_eq!(contacts.block, Some((block, Side::Right)));
+        assert_eq!(body.rect().right(), 500.);
+    }
+
+    #[test]
+    fn sweeps_catch_bodies_passing_through_each_other() {
+        let target = Rect::new(500., 500., 40., 40.);
+        let item = Rect::new(100., 510., 20., 20.);
+        assert!(sweep_overlaps(&item, vec2(1000., 0.), &target));
+        assert!(!sweep_overlaps(&item, vec2(1000., -100.), &target));
+        assert!(!sweep_overlaps(&item, vec2(300., 0.), &target));
+        // Moving away from a touching edge is not a hit.
+        let touching = Rect::new(540., 510., 20., 20.);
+        assert!(!sweep_overlaps(&touching, vec2(50., 0.), &target));
+    }
+
+    #[test]
+    fn only_solid_platforms_stop_bodies_from_below_and_the_sides() {
+        for solidity in [Solidity::Solid, Solidity::OneWay] {
+            let mut level = Level::new(&CAMPAIGN[0].level_file());
+            level.platforms = vec![Platform::new(Rect::new(400., 600., 200., 20.), solidity)];
+            level.blocks = Arena::new();
+            let colliders = Colliders::new(&level);
+            let solid = solidity == Solidity::Solid;
+
+            // Jumping up into the platform.
+            let mut body = Body::new(vec2(490., 625.), vec2(20., 20.));
+            body.velocity = vec2(0., -600.);
+            let from = body.position;
+            body.advance(DT);
+            let contacts = move_body(
+                &mut body,
+                from,
+                &BodyOptions::CHARACTER,
+                Carry::default(),
+                &colliders,
+            );
+            assert_eq!(contacts.ceiling, solid);
+            assert_eq!(body.rect().top() == 620., solid);
+
+            // Running into its side.
+            let mut body = Body::new(vec2(375., 600.), vec2(20., 20.));
+            body.velocity = vec2(600., 0.);
+            let from = body.position;
+            body.advance(DT);
+            let contacts = move_body(
+                &mut body,
+                from,
+                &BodyOptions::CHARACTER,
+                Carry::default(),
+                &colliders,
+            );
+            assert_eq!(contacts.wall, solid.then_some(Side::Right));
+            assert_eq!(body.rect().right() == 400., solid);
         }
     }
 
-    // Item vs. Surfaces (Ground, Platforms, Blocks)
-    for surface in &colliders {
-        if item_rect.overlaps(surface) {
-            if item.velocity.y >= 0. {
-                let previous_item_bottom =
-                    item.position.y + item.size.y - item.velocity.y * get_frame_time();
-                if previous_item_bottom <= surface.y {
-                    // Collision from above
-                    if let ItemState::Thrown = item.state {
-                        if item.velocity.length() > ITEM_MIN_BOUNCE_SPEED {
-                            item.position.y = surface.y - item.size.y;
-                            item.velocity.y = -item.velocity.y * ITEM_BOUNCE_ENERGY_LOSS;
-                            item.velocity.x *= 1.0 - ITEM_BOUNCE_ENERGY_LOSS;
-                        } else {
-                            item.state = ItemState::Idle;
-                            item.on_ground = true;
-                            item.velocity = Vec2::ZERO;
-                            item.position.y = surface.y - item.size.y;
-                        }
-                    } else {
-                        item.on_ground = true;
-                        item.velocity = Vec2::ZERO;
-                        item.position.y = surface.y - item.size.y;
-                    }
-                    return;
-                }
-            }
-            if item_rect.overlaps(surface) {
-                item.velocity.x = -item.velocity.x * ITEM_BOUNCE_ENERGY_LOSS;
-                return;
+    #[test]
+    fn bodies_drop_through_a_one_way_platform_until_they_clear_it() {
+        let mut level = Level::new(&CAMPAIGN[0].level_file());
+        level.platforms = vec![Platform::new(
+            Rect::new(400., 600., 200., 20.),
+            Solidity::OneWay,
+        )];
+        level.blocks = Arena::new();
+        let colliders = Colliders::new(&level);
+
+        let mut body = Body::new(vec2(490., 580.), vec2(20., 20.));
+        assert_eq!(colliders.one_way_platform_under(&body.rect()), Some(0));
+        body.dropping_through = Some(0);
+        let mut other = body.clone();
+        other.dropping_through = None;
+
+        for _ in 0..30 {
+            for body in [&mut body, &mut other] {
+                body.apply_gravity(DT);
+                let from = body.position;
+                body.advance(DT);
+                move_body(
+                    body,
+                    from,
+                    &BodyOptions::CHARACTER,
+                    Carry::default(),
+                    &colliders,
+                );
             }
         }
+        // Only the body that dropped fell through, and it no longer ignores the platform.
+        assert!(body.rect().top() > 620.);
+        assert_eq!(body.dropping_through, None);
+        assert_eq!(other.rect().bottom(), 600.);
+        assert!(other.on_ground);
     }
-}
 
-/// Resolves collisions for a single block with the level and other blocks.
-pub fn resolve_block_collisions(
-    block: &mut Block,
-    platforms: &[Rect],
-    blocks_before: &[Block],
-    blocks_after: &[Block],
-    ground: &Rect,
-    left_wall: &Rect,
-    right_wall: &Rect,
-) {
-    block.on_ground = false;
-    let block_rect = block.rect();
-
-    // Block vs. Walls
-    if block_rect.overlaps(left_wall) {
-        block.position.x = left_wall.right();
-        block.velocity.x = 0.;
-    }
-    if block_rect.overlaps(right_wall) {
-        block.position.x = right_wall.left() - block.size.x;
-        block.velocity.x = 0.;
+    /// Returns the colliders of a level with a single slope rising from the ground to the right.
+    fn level_with_slope() -> (Colliders, Slope) {
+        let mut level = Level::new(&CAMPAIGN[0].level_file());
+        let floor = level.ground.top();
+        let slope = Slope {
+            rect: Rect::new(400., floor - 200., 200., 200.),
+            rises_right: true,
+        };
+        level.platforms.clear();
+        level.slopes = vec![slope];
+        level.blocks = Arena::new();
+        (Colliders::new(&level), slope)
     }
 
-    // Combine all other solid objects for collision
-    let mut colliders = platforms.to_vec();
-    colliders.push(*ground);
-    for other_block in blocks_before.iter().chain(blocks_after.iter()) {
-        if other_block.state == BlockState::Idle {
-            colliders.push(other_block.rect());
+    #[test]
+    fn characters_walk_up_and_down_slopes() {
+        let (colliders, slope) = level_with_slope();
+        let floor = colliders.ground.top();
+        let mut body = Body::new(vec2(300., floor - 20.), vec2(20., 20.));
+        body.on_ground = true;
+
+        for speed in [300., -300.] {
+            for _ in 0..40 {
+                body.velocity.x = speed;
+                body.apply_gravity(DT);
+                let from = body.position;
+                body.advance(DT);
+                move_body(
+                    &mut body,
+                    from,
+                    &BodyOptions::CHARACTER,
+                    Carry::default(),
+                    &colliders,
+                );
+                // The character stays on the ground or on the slope, going up or down.
+                assert!(body.on_ground);
+                let surface = slope.surface_under(&body.rect()).min(floor);
+                assert!((body.rect().bottom() - surface).abs() < 0.1);
+            }
         }
+        assert!(colliders.supports(vec2(450., slope.surface_at(450.) + 20.)));
     }
 
-    // Block vs. Surfaces (Ground, Platforms, other Blocks)
-    if block.velocity.y >= 0. {
-        for surface in &colliders {
-            if block.rect().overlaps(surface) {
-                let previous_block_bottom =
-                    block.position.y + block.size.y - block.velocity.y * get_frame_time();
-                if previous_block_bottom <= surface.y {
-                    block.position.y = surface.y - block.size.y;
-                    block.velocity = Vec2::ZERO;
-                    block.on_ground = true;
-                    return;
-                }
+    #[test]
+    fn blocks_slide_down_slopes_and_rest_on_flat_ground() {
+        let (colliders, slope) = level_with_slope();
+        let floor = colliders.ground.top();
+        let block = Block::new(vec2(520., slope.rect.top() - 60.));
+        let options = block.body_options();
+        let mut body = block.body;
+
+        for _ in 0..240 {
+            if body.on_ground {
+                break;
             }
+            body.apply_gravity(DT);
+            let from = body.position;
+            body.advance(DT);
+            move_body(&mut body, from, &options, Carry::default(), &colliders);
         }
+        assert!(body.on_ground);
+        assert_eq!(body.rect().bottom(), floor);
+        assert!(body.rect().right() <= slope.rect.left() + 1.);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bodies_embedded_in_a_block_are_pushed_out() {
+        let mut colliders = empty_level();
+        let block = Arena::<Block>::new().insert(Block::new(vec2(500., 500.)));
+        colliders.insert_block(block, Rect::new(500., 500., 60., 60.));
+
+        let mut body = Body::new(vec2(545., 520.), vec2(20., 20.));
+        move_body(
+            &mut body,
+            vec2(545., 520.),
+            &BodyOptions::CHARACTER,
+            Carry::default(),
+            &colliders,
+        );
+        assert_eq!(body.rect().left(), 560.);
+    }
+}
@@ -0,0 +1,166 @@
+//! Platforms Module
+//!
+//! This module defines the paths that moving platforms follow: a list of waypoints visited at
+//! a constant speed, with a pause at each of them.
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How a moving platform goes on once it reaches the last waypoint of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathMode {
+    /// The platform stops at the last waypoint.
+    Linear,
+    /// The platform goes back through the waypoints in reverse order, and so on.
+    PingPong,
+    /// The platform goes straight back to the first waypoint and starts over.
+    Loop,
+}
+
+/// A point of a moving platform's path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Waypoint {
+    /// Where the platform's top-left corner goes.
+    pub position: Vec2,
+    /// How long, in seconds, the platform waits there before leaving for the next waypoint.
+    pub wait: f32,
+}
+
+/// The path of a moving platform, and how far along it the platform is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformPath {
+    waypoints: Vec<Waypoint>,
+    mode: PathMode,
+    /// The speed of the platform between waypoints, in pixels per second.
+    speed: f32,
+    /// The waypoint the platform is heading to, or `None` once a linear path is finished.
+    target: Option<usize>,
+    /// Whether a ping-pong path is being followed backwards.
+    reversed: bool,
+    /// How long the platform still waits at the waypoint it last reached.
+    wait: f32,
+}
+
+impl PlatformPath {
+    /// Creates a path starting at the first waypoint, where the platform waits before leaving.
+    /// A path with fewer than two waypoints never moves.
+    pub fn new(waypoints: Vec<Waypoint>, mode: PathMode, speed: f32) -> Self {
+        let wait = waypoints.first().map_or(0.0, |waypoint| waypoint.wait);
+        Self {
+            target: (waypoints.len() > 1).then_some(1),
+            waypoints,
+            mode,
+            speed,
+            reversed: false,
+            wait,
+        }
+    }
+
+    /// Returns where the platform starts.
+    pub fn start(&self) -> Option<Vec2> {
+        self.waypoints.first().map(|waypoint| waypoint.position)
+    }
+
+    /// Returns the waypoint that follows `target`, according to the path's mode.
+    fn next_target(&mut self, target: usize) -> Option<usize> {
+        let last = self.waypoints.len() - 1;
+        match self.mode {
+            PathMode::Linear => (target < last).then_some(target + 1),
+            PathMode::Loop => Some((target + 1) % self.waypoints.len()),
+            PathMode::PingPong => {
+                if target == last {
+                    self.reversed = true;
+                } else if target == 0 {
+                    self.reversed = false;
+                }
+                Some(if self.reversed {
+                    target - 1
+                } else {
+                    target + 1
+                })
+            }
+        }
+    }
+
+    /// Moves a platform at `position` along the path for `dt` seconds and returns its new
+    /// position. Several waypoints can be reached during a single step.
+    pub fn advance(&mut self, mut position: Vec2, dt: f32) -> Vec2 {
+        let mut time = dt;
+        // Each waypoint is reached at most twice a step, however short the path's segments.
+        for _ in 0..=2 * self.waypoints.len() {
+            if self.wait > 0.0 {
+                let waited = self.wait.min(time);
+                self.wait -= waited;
+                time -= waited;
+            }
+            let Some(target) = self.target else {
+                break;
+            };
+            if time <= 0.0 || self.speed <= 0.0 {
+                break;
+            }
+
+            let waypoint = self.waypoints[target];
+            let to_target = waypoint.position - position;
+            let distance = to_target.length();
+            let reach = self.speed * time;
+            if reach < distance {
+                position += to_target / distance * reach;
+                break;
+            }
+            position = waypoint.position;
+            time -= distance / self.speed;
+            self.wait = waypoint.wait;
+            self.target = self.next_target(target);
+        }
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoints(points: &[(f32, f32)], wait: f32) -> Vec<Waypoint> {
+        points
+            .iter()
+            .map(|&(x, y)| Waypoint {
+                position: vec2(x, y),
+                wait,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paths_follow_their_mode_at_the_last_waypoint() {
+        let points = [(0., 0.), (100., 0.), (100., 100.)];
+        let mut linear = PlatformPath::new(waypoints(&points, 0.0), PathMode::Linear, 100.0);
+        let mut ping_pong = PlatformPath::new(waypoints(&points, 0.0), PathMode::PingPong, 100.0);
+        let mut looping = PlatformPath::new(waypoints(&points, 0.0), PathMode::Loop, 100.0);
+
+        // After 2.5 seconds, every platform has reached the last waypoint and gone on for 50px.
+        let linear_end = linear.advance(Vec2::ZERO, 2.5);
+        let ping_pong_end = ping_pong.advance(Vec2::ZERO, 2.5);
+        let loop_end = looping.advance(Vec2::ZERO, 2.5);
+        assert_eq!(linear_end, vec2(100., 100.));
+        assert_eq!(ping_pong_end, vec2(100., 50.));
+        // Going back to the start is a diagonal of about 141px.
+        let back = vec2(-1., -1.).normalize() * 50.;
+        assert!((loop_end - (vec2(100., 100.) + back)).length() < 0.01);
+
+        assert_eq!(linear.advance(linear_end, 1.0), vec2(100., 100.));
+        assert_eq!(ping_pong.advance(ping_pong_end, 1.0), vec2(50., 0.));
+    }
+
+    #[test]
+    fn platforms_wait_at_each_waypoint() {
+        let points = [(0., 0.), (100., 0.)];
+        let mut path = PlatformPath::new(waypoints(&points, 0.5), PathMode::PingPong, 100.0);
+        let mut position = path.advance(Vec2::ZERO, 0.5);
+        assert_eq!(position, Vec2::ZERO);
+        position = path.advance(position, 1.25);
+        assert_eq!(position, vec2(100., 0.));
+        position = path.advance(position, 0.5);
+        assert_eq!(position, vec2(75., 0.));
+    }
+}
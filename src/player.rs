@@ -3,13 +3,17 @@
 //! This module defines the player's behavior and properties.
 
 use crate::{
+    arena::{Arena, Handle},
     blocks::{Block, BlockState},
+    body::Body,
     constants::{
-        BLOCK_OFFSET, GRAVITY, GROUND_HEIGHT, ITEM_THROW_SPEED, JUMP_FORCE, PLAYER_COLOR,
-        PLAYER_SIZE, PLAYER_SPAWN_X, PLAYER_SPEED,
+        BLOCK_MASS, BLOCK_OFFSET, ITEM_THROW_SPEED, JUMP_FORCE, PLAYER_BLINK_INTERVAL,
+        PLAYER_COLOR, PLAYER_INVULNERABILITY_DURATION, PLAYER_KNOCKBACK_DURATION,
+        PLAYER_KNOCKBACK_SPEED, PLAYER_LIVES, PLAYER_MAX_HEALTH, PLAYER_SIZE, PLAYER_SPEED,
     },
+    input::InputFrame,
     items::{Item, ItemState},
-    level::LEVEL_HEIGHT,
+    physics::Carry,
 };
 use macroquad::prelude::*;
 
@@ -22,75 +26,135 @@ pub enum PlayerState {
 }
 
 /// Represents the object a player is holding.
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum HeldObject {
-    Item(usize),
-    Block(usize),
+    Item(Handle<Item>),
+    Block(Handle<Block>),
 }
 
 /// Represents the player character in the game.
 pub struct Player {
-    pub position: Vec2,
-    pub size: Vec2,
-    pub velocity: Vec2,
-    pub on_ground: bool,
+    pub body: Body,
     pub state: PlayerState,
     pub facing_right: bool,
     pub held_object: Option<HeldObject>,
+    /// The hits the player can still take before losing a life.
+    pub health: u32,
+    /// The lives left, including the current one. The run is over when none are left.
+    pub lives: u32,
+    /// Where the player comes back after losing a life: the spawn point, then the last
+    /// checkpoint reached.
+    pub respawn_point: Vec2,
+    /// How long the player still cannot be hurt, in seconds.
+    pub invulnerable_timer: f32,
+    /// How long the player is still being knocked back by a hit, in seconds.
+    pub knockback_timer: f32,
 }
 
 impl Player {
-    /// Creates a new player instance with default values.
-    pub fn new() -> Self {
+    /// Creates a new player at `spawn`, the top-left corner of where they start.
+    pub fn new(spawn: Vec2) -> Self {
         Self {
-            position: vec2(PLAYER_SPAWN_X, LEVEL_HEIGHT - GROUND_HEIGHT - PLAYER_SIZE),
-            size: vec2(PLAYER_SIZE, PLAYER_SIZE),
-            velocity: Vec2::new(0., 0.),
-            on_ground: false,
+            body: Body::new(spawn, vec2(PLAYER_SIZE, PLAYER_SIZE)),
             state: PlayerState::Idle,
             facing_right: true,
             held_object: None,
+            health: PLAYER_MAX_HEALTH,
+            lives: PLAYER_LIVES,
+            respawn_point: spawn,
+            invulnerable_timer: 0.0,
+            knockback_timer: 0.0,
         }
     }
 
-    /// Returns the player's bounding box as a `Rect`.
-    pub fn rect(&self) -> Rect {
-        Rect::new(self.position.x, self.position.y, self.size.x, self.size.y)
+    /// Returns whether the player was hurt or respawned too recently to be hurt again.
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_timer > 0.0
+    }
+
+    /// Hurts the player, who is knocked back away from `from_x` and cannot be hurt again for a
+    /// while. The last hit of a life loses it.
+    pub fn take_hit(&mut self, from_x: f32) {
+        self.health = self.health.saturating_sub(1);
+        if self.health == 0 {
+            self.lose_life();
+            return;
+        }
+        let away = if self.body.rect().center().x < from_x {
+            -1.0
+        } else {
+            1.0
+        };
+        self.body.velocity = vec2(away * PLAYER_KNOCKBACK_SPEED, -PLAYER_KNOCKBACK_SPEED);
+        self.body.on_ground = false;
+        self.invulnerable_timer = PLAYER_INVULNERABILITY_DURATION;
+        self.knockback_timer = PLAYER_KNOCKBACK_DURATION;
+    }
+
+    /// Takes a life from the player and, if any are left, brings them back at their respawn
+    /// point with full health, letting go of what they held.
+    pub fn lose_life(&mut self) {
+        self.lives = self.lives.saturating_sub(1);
+        self.health = PLAYER_MAX_HEALTH;
+        self.held_object = None;
+        self.body = Body::new(self.respawn_point, self.body.size);
+        self.invulnerable_timer = PLAYER_INVULNERABILITY_DURATION;
+        self.knockback_timer = 0.0;
+    }
+
+    /// Returns how far the held object, if any, sticks out in front of the player.
+    pub fn carry(&self, items: &Arena<Item>, blocks: &Arena<Block>) -> Carry {
+        let width = match self.held_object {
+            Some(HeldObject::Item(handle)) => items.get(handle).map_or(0.0, |i| i.body.size.x),
+            Some(HeldObject::Block(handle)) => blocks.get(handle).map_or(0.0, |b| b.body.size.x),
+            None => 0.0,
+        };
+        Carry {
+            width,
+            facing_right: self.facing_right,
+        }
     }
 
     /// Updates the player's state, including position, velocity, and state, based on input and physics.
-    pub fn update(&mut self, dt: f32) {
+    pub fn update(&mut self, input: &InputFrame, dt: f32) {
+        let body = &mut self.body;
+        self.invulnerable_timer = (self.invulnerable_timer - dt).max(0.0);
+        self.knockback_timer = (self.knockback_timer - dt).max(0.0);
+
         // Apply gravity
-        self.velocity.y += GRAVITY * dt;
+        body.apply_gravity(dt);
 
         // Handle input
-        if is_key_down(KeyCode::Right) {
-            self.velocity.x = PLAYER_SPEED;
-            self.facing_right = true;
-        } else if is_key_down(KeyCode::Left) {
-            self.velocity.x = -PLAYER_SPEED;
-            self.facing_right = false;
-        } else {
-            self.velocity.x = 0.;
-        }
+        // The player has no control while being knocked back.
+        if self.knockback_timer <= 0.0 {
+            // Down and jump together drop through a one-way platform instead of jumping.
+            if input.jump && !input.down && body.on_ground {
+                body.jump(JUMP_FORCE);
+            }
 
-        if is_key_pressed(KeyCode::Up) && self.on_ground {
-            self.velocity.y = -JUMP_FORCE;
-            self.on_ground = false;
+            if input.right {
+                body.walk(PLAYER_SPEED);
+                self.facing_right = true;
+            } else if input.left {
+                body.walk(-PLAYER_SPEED);
+                self.facing_right = false;
+            } else {
+                body.walk(0.);
+            }
         }
 
         // Update position
-        self.position += self.velocity * dt;
+        body.advance(dt);
 
         // Update state
-        if self.on_ground {
-            if self.velocity.x.abs() > 0.1 {
+        if body.on_ground {
+            if body.velocity.x.abs() > 0.1 {
                 self.state = PlayerState::Run;
             } else {
                 self.state = PlayerState::Idle;
             }
         } else {
-            if self.velocity.y < 0. {
+            if body.velocity.y < 0. {
                 self.state = PlayerState::Jump;
             } else {
                 self.state = PlayerState::Fall;
@@ -98,85 +162,106 @@ impl Player {
         }
     }
 
-    /// Draws the player on the screen.
-    pub fn draw(&self) {
-        draw_rectangle(
-            self.position.x,
-            self.position.y,
-            self.size.x,
-            self.size.y,
-            PLAYER_COLOR,
-        );
+    /// Draws the player on the screen, interpolated by `alpha` between its last two positions.
+    /// An invulnerable player blinks.
+    pub fn draw(&self, alpha: f32) {
+        let blink = (self.invulnerable_timer / PLAYER_BLINK_INTERVAL) as u32;
+        if self.is_invulnerable() && blink % 2 == 1 {
+            return;
+        }
+        self.body.draw(alpha, PLAYER_COLOR);
     }
 
     /// Handles player interactions with items and blocks (grabbing, dropping, throwing).
-    pub fn process_interactions(&mut self, items: &mut [Item], blocks: &mut [Block]) {
-        let space_pressed = is_key_pressed(KeyCode::Space);
+    /// A held object that no longer exists is let go of.
+    pub fn process_interactions(
+        &mut self,
+        items: &mut Arena<Item>,
+        blocks: &mut Arena<Block>,
+        input: &InputFrame,
+    ) {
+        let space_pressed = input.grab_throw;
 
         match self.held_object {
-            Some(HeldObject::Item(idx)) => {
-                let item = &mut items[idx];
+            Some(HeldObject::Item(handle)) => {
+                let Some(item) = items.get_mut(handle) else {
+                    self.held_object = None;
+                    return;
+                };
                 if space_pressed {
                     item.state = ItemState::Thrown;
-                    item.on_ground = false;
+                    item.body.on_ground = false;
                     let dir = if self.facing_right { 1.0 } else { -1.0 };
-                    item.velocity = self.velocity + vec2(dir, -1.0).normalize() * ITEM_THROW_SPEED;
+                    item.body.velocity =
+                        self.body.velocity + vec2(dir, -1.0).normalize() * ITEM_THROW_SPEED;
                     self.held_object = None;
                 } else {
                     // Keep item hooked to player
-                    item.position.y = self.position.y;
-                    item.position.x = if self.facing_right {
-                        self.position.x + self.size.x
+                    item.body.position.y = self.body.position.y;
+                    item.body.position.x = if self.facing_right {
+                        self.body.position.x + self.body.size.x
                     } else {
-                        self.position.x - item.size.x
+                        self.body.position.x - item.body.size.x
                     };
                 }
             }
-            Some(HeldObject::Block(idx)) => {
-                let block = &mut blocks[idx];
+            Some(HeldObject::Block(handle)) => {
+                let Some(block) = blocks.get_mut(handle) else {
+                    self.held_object = None;
+                    return;
+                };
                 if space_pressed {
-                    block.state = BlockState::Idle;
-                    block.on_ground = false;
+                    // Holding down drops the block at the player's feet instead of throwing it.
+                    if input.down {
+                        block.state = BlockState::Idle;
+                    } else {
+                        block.state = BlockState::Thrown;
+                        let dir = if self.facing_right { 1.0 } else { -1.0 };
+                        block.body.velocity = self.body.velocity
+                            + vec2(dir, -1.0).normalize() * ITEM_THROW_SPEED / BLOCK_MASS;
+                    }
+                    block.body.on_ground = false;
                     self.held_object = None;
                 } else {
                     // Keep block hooked to player
-                    block.position.y = self.position.y - BLOCK_OFFSET;
-                    block.position.x = if self.facing_right {
-                        self.position.x + self.size.x
+                    block.body.position.y = self.body.position.y - BLOCK_OFFSET;
+                    block.body.position.x = if self.facing_right {
+                        self.body.position.x + self.body.size.x
                     } else {
-                        self.position.x - block.size.x
+                        self.body.position.x - block.body.size.x
                     };
                 }
             }
             None => {
                 // Try to grab an object
                 if space_pressed {
-                    let player_rect = self.rect();
+                    let player_rect = self.body.rect();
                     // Prioritize grabbing items
-                    for (i, item) in items.iter_mut().enumerate() {
-                        if item.state == ItemState::Idle && player_rect.overlaps(&item.rect()) {
+                    for (handle, item) in items.iter_mut() {
+                        if item.body_options().holdable && player_rect.overlaps(&item.body.rect()) {
                             item.state = ItemState::Hooked;
-                            item.velocity = Vec2::ZERO;
-                            self.held_object = Some(HeldObject::Item(i));
+                            item.body.velocity = Vec2::ZERO;
+                            self.held_object = Some(HeldObject::Item(handle));
                             return; // Exit after grabbing one object
                         }
                     }
                     // If no item was grabbed, try to grab a block
-                    for (i, block) in blocks.iter_mut().enumerate() {
+                    for (handle, block) in blocks.iter_mut() {
                         // Player cannot grab a block they are standing on.
-                        let player_is_on_block = self.on_ground
-                            && self.rect().bottom() >= block.rect().top()
-                            && self.rect().bottom() <= block.rect().top() + 1.0
+                        let block_rect = block.body.rect();
+                        let player_is_on_block = self.body.on_ground
+                            && player_rect.bottom() >= block_rect.top()
+                            && player_rect.bottom() <= block_rect.top() + 1.0
                             // Tolerance
-                            && player_rect.overlaps(&block.rect());
+                            && player_rect.overlaps(&block_rect);
 
                         if !player_is_on_block
-                            && block.state == BlockState::Idle
-                            && player_rect.overlaps(&block.rect())
+                            && block.body_options().holdable
+                            && player_rect.overlaps(&block_rect)
                         {
                             block.state = BlockState::Hooked;
-                            block.velocity = Vec2::ZERO;
-                            self.held_object = Some(HeldObject::Block(i));
+                            block.body.velocity = Vec2::ZERO;
+                            self.held_object = Some(HeldObject::Block(handle));
                             return; // Exit after grabbing one object
                         }
                     }
@@ -184,4 +269,4 @@ impl Player {
             }
         }
     }
-}
\ No newline at end of file
+}
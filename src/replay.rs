@@ -0,0 +1,409 @@
+//! Replay Module
+//!
+//! This module records the input of every simulation step of a run, together with the seed
+//! it was generated from, and plays it back to reproduce the run step for step. A checksum
+//! of the world (player position, baddie count, remaining keys) is stored periodically so
+//! that playback can detect when the simulation diverges from the recording.
+//!
+//! Replay files use a compact little-endian binary layout:
+//!
+//! ```text
+//! magic "PLRP" | version u8 | seed u64 | step rate f32 | checksum interval u32
+//! run count u32 | runs of (input flags u8, repeat count u16)
+//! checksum count u32 | checksums of (step u32, checksum u64)
+//! ```
+
+use crate::input::InputFrame;
+use crate::world::World;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PLRP";
+const VERSION: u8 = 1;
+
+/// The number of steps between two stored world checksums.
+pub const CHECKSUM_INTERVAL: u32 = 60;
+
+/// An error raised while reading or playing back a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// The file is not a replay, or is truncated or corrupted.
+    InvalidFormat(String),
+    /// The world's checksum differs from the recorded one after the given step.
+    Desync {
+        step: u32,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::InvalidFormat(message) => write!(f, "invalid replay file: {}", message),
+            ReplayError::Desync {
+                step,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "desync after step {}: expected checksum {:016x}, got {:016x}",
+                step, expected, actual
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(err: std::io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+/// Computes a checksum of the parts of the world a desync would show up in: the player's
+/// position, the number of baddies and the number of keys left.
+pub fn world_checksum(world: &World) -> u64 {
+    // 64-bit FNV-1a, which is stable across platforms and Rust versions.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    feed(&world.player.body.position.x.to_bits().to_le_bytes());
+    feed(&world.player.body.position.y.to_bits().to_le_bytes());
+    feed(&(world.baddies.len() as u32).to_le_bytes());
+    feed(&(world.level.keys.len() as u32).to_le_bytes());
+    hash
+}
+
+/// Packs an input frame into one byte.
+fn input_to_bits(input: &InputFrame) -> u8 {
+    input.left as u8
+        | (input.right as u8) << 1
+        | (input.jump as u8) << 2
+        | (input.grab_throw as u8) << 3
+        | (input.down as u8) << 4
+}
+
+/// Unpacks an input frame from one byte.
+fn input_from_bits(bits: u8) -> InputFrame {
+    InputFrame {
+        left: bits & 1 != 0,
+        right: bits & 1 << 1 != 0,
+        jump: bits & 1 << 2 != 0,
+        grab_throw: bits & 1 << 3 != 0,
+        down: bits & 1 << 4 != 0,
+    }
+}
+
+/// A recorded run: its seed, step rate, per-step input and periodic world checksums.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// The number of simulation steps per second the run was recorded at.
+    pub step_rate: f32,
+    pub inputs: Vec<InputFrame>,
+    /// `(step, checksum)` pairs, where `step` is the number of steps simulated so far.
+    pub checksums: Vec<(u32, u64)>,
+}
+
+impl Replay {
+    /// Creates an empty replay for a run generated from `seed` and stepped at `step_rate`.
+    pub fn new(seed: u64, step_rate: f32) -> Self {
+        Self {
+            seed,
+            step_rate,
+            inputs: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Appends the input of a step that was just simulated, storing a checksum of the
+    /// resulting world every `CHECKSUM_INTERVAL` steps.
+    pub fn record(&mut self, input: &InputFrame, world: &World) {
+        self.inputs.push(*input);
+        let step = self.inputs.len() as u32;
+        if step.is_multiple_of(CHECKSUM_INTERVAL) {
+            self.checksums.push((step, world_checksum(world)));
+        }
+    }
+
+    /// Checks the world against the checksum recorded after `step` steps, if there is one.
+    pub fn check(&self, step: u32, world: &World) -> Result<(), ReplayError> {
+        let Ok(index) = self.checksums.binary_search_by_key(&step, |(s, _)| *s) else {
+            return Ok(());
+        };
+        let expected = self.checksums[index].1;
+        let actual = world_checksum(world);
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(ReplayError::Desync {
+                step,
+                expected,
+                actual,
+            })
+        }
+    }
+
+    /// Plays the whole replay back in a fresh world, without rendering, and returns the final
+    /// world, or the first desync found.
+    pub fn verify(&self) -> Result<World, ReplayError> {
+        let mut world = World::new(self.seed);
+        let dt = 1.0 / self.step_rate;
+        for (i, input) in self.inputs.iter().enumerate() {
+            world.step(input, dt);
+            self.check(i as u32 + 1, &world)?;
+        }
+        Ok(world)
+    }
+
+    /// Encodes the replay in its binary file format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut runs: Vec<(u8, u16)> = Vec::new();
+        for input in &self.inputs {
+            let bits = input_to_bits(input);
+            match runs.last_mut() {
+                Some((last, count)) if *last == bits && *count < u16::MAX => *count += 1,
+                _ => runs.push((bits, 1)),
+            }
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.step_rate.to_le_bytes());
+        bytes.extend_from_slice(&CHECKSUM_INTERVAL.to_le_bytes());
+        bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+        for (bits, count) in runs {
+            bytes.push(bits);
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.checksums.len() as u32).to_le_bytes());
+        for (step, checksum) in &self.checksums {
+            bytes.extend_from_slice(&step.to_le_bytes());
+            bytes.extend_from_slice(&checksum.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a replay from its binary file format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(ReplayError::InvalidFormat("not a replay file".to_owned()));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(ReplayError::InvalidFormat(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let seed = u64::from_le_bytes(reader.array()?);
+        let step_rate = f32::from_le_bytes(reader.array()?);
+        // The interval is informative: checksums are stored with their step number.
+        let _checksum_interval = u32::from_le_bytes(reader.array()?);
+
+        let mut inputs = Vec::new();
+        let run_count = u32::from_le_bytes(reader.array()?);
+        for _ in 0..run_count {
+            let input = input_from_bits(reader.take(1)?[0]);
+            let count = u16::from_le_bytes(reader.array()?);
+            inputs.extend(std::iter::repeat_n(input, count as usize));
+        }
+
+        let mut checksums = Vec::new();
+        let checksum_count = u32::from_le_bytes(reader.array()?);
+        for _ in 0..checksum_count {
+            let step = u32::from_le_bytes(reader.array()?);
+            let checksum = u64::from_le_bytes(reader.array()?);
+            checksums.push((step, checksum));
+        }
+
+        if reader.offset != bytes.len() {
+            return Err(ReplayError::InvalidFormat(
+                "unexpected data after the checksums".to_owned(),
+            ));
+        }
+
+        Ok(Self {
+            seed,
+            step_rate,
+            inputs,
+            checksums,
+        })
+    }
+
+    /// Reads a replay file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the replay to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// A cursor over the bytes of a replay file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the next `len` bytes.
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        let end = self.offset + len;
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or_else(|| ReplayError::InvalidFormat("file is truncated".to_owned()))?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    /// Reads the next `N` bytes as an array.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        Ok(self
+            .take(N)?
+            .try_into()
+            .expect("slice has the requested length"))
+    }
+}
+
+/// Feeds the inputs of a replay to the game, one step at a time, and checks for desyncs.
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    /// The number of steps played so far.
+    pub step: u32,
+}
+
+impl ReplayPlayer {
+    /// Creates a player starting at the beginning of the replay.
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, step: 0 }
+    }
+
+    /// Returns the input for the next step, or `None` when the replay is over.
+    pub fn next_input(&mut self) -> Option<InputFrame> {
+        let input = self.replay.inputs.get(self.step as usize).copied()?;
+        self.step += 1;
+        Some(input)
+    }
+
+    /// Checks the world after the step that was just played.
+    pub fn check(&self, world: &World) -> Result<(), ReplayError> {
+        self.replay.check(self.step, world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::PHYSICS_STEP_RATE;
+
+    /// Records a scripted run of `steps` steps.
+    fn record_run(seed: u64, steps: u32) -> Replay {
+        let mut world = World::new(seed);
+        let mut replay = Replay::new(seed, PHYSICS_STEP_RATE);
+        for step in 0..steps {
+            let input = InputFrame {
+                left: step % 500 >= 250,
+                right: step % 500 < 250,
+                down: step % 210 == 0,
+                jump: step % 70 == 0,
+                grab_throw: step % 150 == 0,
+            };
+            world.step(&input, 1.0 / PHYSICS_STEP_RATE);
+            replay.record(&input, &world);
+        }
+        replay
+    }
+
+    #[test]
+    fn replay_round_trips_through_the_file_format() {
+        let replay = record_run(7, 1000);
+        let bytes = replay.to_bytes();
+        assert_eq!(Replay::from_bytes(&bytes).unwrap(), replay);
+        // Run-length encoding keeps long runs of identical input small.
+        assert!(bytes.len() < replay.inputs.len());
+    }
+
+    #[test]
+    fn recorded_run_plays_back_without_desync() {
+        let replay = record_run(11, 2000);
+        assert!(!replay.checksums.is_empty());
+        replay.verify().unwrap();
+    }
+
+    #[test]
+    fn playback_detects_a_desync() {
+        let mut replay = record_run(11, 600);
+        replay.inputs[10].right = !replay.inputs[10].right;
+        assert!(matches!(
+            replay.verify(),
+            Err(ReplayError::Desync { step: 60, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let bytes = record_run(3, 100).to_bytes();
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ReplayError::InvalidFormat(_))
+        ));
+    }
+
+    /// The seeds of the scripted runs stored in `replays/` by `record_stored_replays`.
+    const STORED_SEEDS: [u64; 2] = [1, 2];
+
+    /// Returns the directory of the stored replays.
+    fn stored_replays_dir() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("replays")
+    }
+
+    /// Plays back every replay stored in the `replays/` directory, so recorded runs act as
+    /// regression tests for the simulation. They were recorded by an earlier build: a desync
+    /// means the physics or the baddies behave differently since.
+    #[test]
+    fn stored_replays_play_back_without_desync() {
+        let dir = stored_replays_dir();
+        let entries = fs::read_dir(&dir)
+            .unwrap_or_else(|err| panic!("cannot read {}: {}", dir.display(), err));
+        let mut verified = 0;
+        for entry in entries {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "replay") {
+                let replay = Replay::load(&path).unwrap();
+                if let Err(err) = replay.verify() {
+                    panic!("{}: {}", path.display(), err);
+                }
+                verified += 1;
+            }
+        }
+        assert!(verified > 0, "no replays stored in {}", dir.display());
+    }
+
+    /// Records the scripted runs stored in `replays/` again, after the physics or the baddies
+    /// changed on purpose. Run with `cargo test record_stored_replays -- --ignored`.
+    #[test]
+    #[ignore]
+    fn record_stored_replays() {
+        let dir = stored_replays_dir();
+        fs::create_dir_all(&dir).unwrap();
+        for seed in STORED_SEEDS {
+            let path = dir.join(format!("seed_{}.replay", seed));
+            record_run(seed, 3600).save(&path).unwrap();
+        }
+    }
+}
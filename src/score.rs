@@ -0,0 +1,127 @@
+//! Score Module
+//!
+//! This module counts the points the player earns during a level, and tallies them with the
+//! bonuses for finishing it once the level is complete.
+
+use crate::constants::{
+    KEY_POINTS, KILL_POINTS, LIFE_BONUS, MULTI_KILL_WINDOW, TIME_BONUS, TIME_BONUS_PER_SECOND,
+};
+
+/// The points earned so far in a level.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Score {
+    /// The baddies killed with thrown objects.
+    pub kills: u32,
+    /// The points earned for those kills, multi-kill bonuses included.
+    pub kill_points: u32,
+    /// The keys collected.
+    pub keys: u32,
+    /// How many kills the current multi-kill counts so far.
+    combo: u32,
+    /// How long the current multi-kill can still be extended, in seconds.
+    combo_timer: f32,
+}
+
+impl Score {
+    /// Creates an empty score.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lets `dt` seconds pass: a multi-kill ends when no baddie was killed for a while.
+    pub fn update(&mut self, dt: f32) {
+        self.combo_timer -= dt;
+        if self.combo_timer <= 0.0 {
+            self.combo = 0;
+        }
+    }
+
+    /// Counts a baddie killed by a thrown object. Each kill of a multi-kill is worth more than
+    /// the one before.
+    pub fn baddie_killed(&mut self) {
+        self.combo += 1;
+        self.combo_timer = MULTI_KILL_WINDOW;
+        self.kills += 1;
+        self.kill_points += KILL_POINTS * self.combo;
+    }
+
+    /// Counts a collected key.
+    pub fn key_collected(&mut self) {
+        self.keys += 1;
+    }
+
+    /// Returns the points earned so far in the level.
+    pub fn points(&self) -> u32 {
+        self.kill_points + self.keys * KEY_POINTS
+    }
+
+    /// Tallies the points of a level completed in `elapsed` seconds with `lives` left.
+    pub fn tally(&self, elapsed: f32, lives: u32) -> LevelTally {
+        let time_penalty = (elapsed.max(0.0) * TIME_BONUS_PER_SECOND as f32) as u32;
+        LevelTally {
+            kill_points: self.kill_points,
+            key_points: self.keys * KEY_POINTS,
+            time_bonus: TIME_BONUS.saturating_sub(time_penalty),
+            lives_bonus: lives * LIFE_BONUS,
+        }
+    }
+}
+
+/// The points of a completed level, as shown on the level-complete screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelTally {
+    pub kill_points: u32,
+    pub key_points: u32,
+    /// The bonus for finishing the level quickly, which shrinks every second.
+    pub time_bonus: u32,
+    /// The bonus for every life left.
+    pub lives_bonus: u32,
+}
+
+impl LevelTally {
+    /// Returns the points the level adds to the run's score.
+    pub fn total(&self) -> u32 {
+        self.kill_points + self.key_points + self.time_bonus + self.lives_bonus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kills_in_quick_succession_are_worth_more() {
+        let mut score = Score::new();
+        score.baddie_killed();
+        score.update(MULTI_KILL_WINDOW / 2.);
+        score.baddie_killed();
+        assert_eq!(score.kill_points, KILL_POINTS + 2 * KILL_POINTS);
+
+        // After a pause, the next kill starts a new multi-kill.
+        score.update(MULTI_KILL_WINDOW);
+        score.baddie_killed();
+        assert_eq!(score.kills, 3);
+        assert_eq!(score.kill_points, 4 * KILL_POINTS);
+    }
+
+    #[test]
+    fn the_tally_adds_the_time_and_lives_bonuses() {
+        let mut score = Score::new();
+        score.key_collected();
+        score.baddie_killed();
+        assert_eq!(score.points(), KEY_POINTS + KILL_POINTS);
+
+        let quick = score.tally(10., 2);
+        assert_eq!(quick.time_bonus, TIME_BONUS - 10 * TIME_BONUS_PER_SECOND);
+        assert_eq!(quick.lives_bonus, 2 * LIFE_BONUS);
+        assert_eq!(
+            quick.total(),
+            score.points() + quick.time_bonus + quick.lives_bonus
+        );
+
+        // The time bonus runs out, but never goes negative.
+        let slow = score.tally(1e6, 0);
+        assert_eq!(slow.time_bonus, 0);
+        assert_eq!(slow.total(), score.points());
+    }
+}
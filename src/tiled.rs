@@ -0,0 +1,696 @@
+//! Tiled Module
+//!
+//! This module imports maps made with the Tiled editor (https://www.mapeditor.org), saved in
+//! its JSON format, into a `LevelFile`. Each tile of the map covers one block-sized cell of the
+//! level and is drawn from the bundled world tileset.
+//!
+//! - Tile layers are drawn, and their tiles are solid ground unless the layer's `solidity`
+//!   property says `OneWay` or `None`.
+//! - Object layers spawn what their objects' type (or class) names: `player`, `exit`,
+//!   `checkpoint`, `key`, `block`, `item` or `baddie`, standing at the middle of the object's
+//!   bottom edge, and `platform` rectangles. A platform's `solidity` property is `Solid` (the
+//!   default) or `OneWay`; its `path` property points at a `path` polyline object to move it
+//!   along, with a `mode` (`Linear`, `PingPong` or `Loop`) and optional `speed` and `wait`.
+//!   Baddies take no properties: there is a single kind of baddie, so a `kind` property is
+//!   reported as an error rather than silently ignored.
+//!
+//! Anything else the game cannot reproduce, such as TMX files, infinite maps, flipped tiles
+//! or another tileset, is reported as an error instead of being silently left out.
+
+use std::collections::HashMap;
+
+use crate::constants::{
+    BADDIE_SIZE, BLOCK_SIZE, ITEM_SIZE, KEY_SIZE, LEVEL_GRID_CELL_SIZE, PLAYER_SIZE,
+    TILESET_COLUMNS, TILESET_PATH, TILESET_TILE_COUNT, TILESET_TILE_SIZE,
+};
+use crate::input::ConfigError;
+use crate::level_file::{Area, Boundaries, LevelFile, PathSpec, PlatformSpec, TileSpec};
+use crate::physics::Solidity;
+use crate::platforms::PathMode;
+use macroquad::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The bits Tiled sets in a tile id to flip or rotate the tile.
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Deserialize)]
+struct Map {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    width: u32,
+    height: u32,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<Layer>,
+    #[serde(default)]
+    tilesets: Vec<Tileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Layer {
+    #[serde(rename = "tilelayer")]
+    Tiles(TileLayer),
+    #[serde(rename = "objectgroup")]
+    Objects(ObjectGroup),
+    #[serde(rename = "imagelayer")]
+    Image(OtherLayer),
+    #[serde(rename = "group")]
+    Group(OtherLayer),
+}
+
+#[derive(Deserialize)]
+struct TileLayer {
+    name: String,
+    #[serde(default)]
+    data: Option<Value>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize)]
+struct ObjectGroup {
+    name: String,
+    #[serde(default)]
+    objects: Vec<Object>,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+}
+
+#[derive(Deserialize)]
+struct OtherLayer {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Object {
+    id: u32,
+    #[serde(default, rename = "type")]
+    kind: String,
+    /// The type of the object in Tiled 1.9 and later.
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    polygon: Option<Value>,
+    #[serde(default)]
+    polyline: Option<Vec<PolylinePoint>>,
+    #[serde(default)]
+    text: Option<Value>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Deserialize)]
+struct PolylinePoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct Tileset {
+    firstgid: u32,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+}
+
+/// Returns an error that is not tied to a line of the map file.
+fn error(message: String) -> ConfigError {
+    ConfigError {
+        line: None,
+        message,
+    }
+}
+
+/// Returns a string property, or `None` if the object or layer does not have it.
+fn string_property<'a>(
+    properties: &'a [Property],
+    name: &str,
+    owner: &str,
+) -> Result<Option<&'a str>, ConfigError> {
+    match properties.iter().find(|property| property.name == name) {
+        None => Ok(None),
+        Some(property) => property
+            .value
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| error(format!("{}: property `{}` must be a string", owner, name))),
+    }
+}
+
+/// Returns a number property, or `None` if the object does not have it.
+fn number_property(
+    properties: &[Property],
+    name: &str,
+    owner: &str,
+) -> Result<Option<f32>, ConfigError> {
+    match properties.iter().find(|property| property.name == name) {
+        None => Ok(None),
+        Some(property) => property
+            .value
+            .as_f64()
+            .map(|value| Some(value as f32))
+            .ok_or_else(|| error(format!("{}: property `{}` must be a number", owner, name))),
+    }
+}
+
+/// Fails on the first property that is not in `supported`.
+fn check_properties(
+    properties: &[Property],
+    supported: &[&str],
+    owner: &str,
+) -> Result<(), ConfigError> {
+    match properties
+        .iter()
+        .find(|property| !supported.contains(&property.name.as_str()))
+    {
+        Some(property) => Err(error(format!(
+            "{}: unsupported property `{}`",
+            owner, property.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parses a `solidity` property, `Solid` if it is missing. `None`, for tiles that are only
+/// drawn, is only allowed when `allow_none` is set.
+fn solidity(
+    properties: &[Property],
+    allow_none: bool,
+    owner: &str,
+) -> Result<Option<Solidity>, ConfigError> {
+    match string_property(properties, "solidity", owner)? {
+        None | Some("Solid") => Ok(Some(Solidity::Solid)),
+        Some("OneWay") => Ok(Some(Solidity::OneWay)),
+        Some("None") if allow_none => Ok(None),
+        Some(other) => Err(error(format!("{}: unknown solidity `{}`", owner, other))),
+    }
+}
+
+/// Imports a Tiled map saved as JSON. JSON syntax errors point at their line; errors about
+/// the map's contents name the layer or object they come from.
+pub fn import(json: &str) -> Result<LevelFile, ConfigError> {
+    let map: Map = serde_json::from_str(json).map_err(|err| ConfigError {
+        line: Some(err.line()),
+        message: err.to_string(),
+    })?;
+    if map.orientation != "orthogonal" {
+        return Err(error(format!(
+            "unsupported {} map: only orthogonal maps are supported",
+            map.orientation
+        )));
+    }
+    if map.infinite {
+        return Err(error("infinite maps are not supported".to_owned()));
+    }
+    if map.width == 0 || map.height == 0 {
+        return Err(error(format!(
+            "invalid map size {} x {}: it must be positive",
+            map.width, map.height
+        )));
+    }
+
+    let [tileset] = map.tilesets.as_slice() else {
+        return Err(error(format!(
+            "the map must use exactly one tileset, {}, not {}",
+            TILESET_PATH,
+            map.tilesets.len()
+        )));
+    };
+    if let Some(source) = &tileset.source {
+        return Err(error(format!(
+            "external tileset {} is not supported: embed it in the map",
+            source
+        )));
+    }
+    let image = tileset.image.as_deref().unwrap_or_default();
+    let file_name = TILESET_PATH.rsplit('/').next().unwrap_or(TILESET_PATH);
+    if !image.ends_with(file_name) {
+        return Err(error(format!(
+            "unsupported tileset image {}: maps must use {}",
+            image, TILESET_PATH
+        )));
+    }
+    if tileset.tilewidth != TILESET_TILE_SIZE
+        || tileset.tileheight != TILESET_TILE_SIZE
+        || tileset.columns != TILESET_COLUMNS
+        || tileset.margin != 0
+        || tileset.spacing != 0
+    {
+        return Err(error(format!(
+            "the tileset must be cut into {} pixel tiles, {} per row, without margin or spacing",
+            TILESET_TILE_SIZE, TILESET_COLUMNS
+        )));
+    }
+
+    // Map pixels are scaled so that a tile of the map covers one cell of the level.
+    let cell = LEVEL_GRID_CELL_SIZE;
+    let scale = vec2(cell / map.tilewidth, cell / map.tileheight);
+    let mut level = LevelFile {
+        size: (map.width as f32 * cell, map.height as f32 * cell),
+        boundaries: Boundaries {
+            ground: 0.,
+            ceiling: 0.,
+            walls: 0.,
+        },
+        player: (0., 0.),
+        exit: (0., 0.),
+        platforms: Vec::new(),
+        slopes: Vec::new(),
+        checkpoints: Vec::new(),
+        keys: Vec::new(),
+        blocks: Vec::new(),
+        items: Vec::new(),
+        baddies: Vec::new(),
+        tiles: Vec::new(),
+    };
+    let mut player = None;
+    let mut exit = None;
+
+    for layer in &map.layers {
+        match layer {
+            Layer::Tiles(layer) => {
+                import_tile_layer(layer, &map, tileset.firstgid, &mut level)?;
+            }
+            Layer::Objects(group) => {
+                let owner = format!("layer `{}`", group.name);
+                if group.offsetx != 0. || group.offsety != 0. {
+                    return Err(error(format!("{}: layer offsets are not supported", owner)));
+                }
+                let paths: HashMap<u32, &Object> = group
+                    .objects
+                    .iter()
+                    .filter(|object| object_type(object) == "path")
+                    .map(|object| (object.id, object))
+                    .collect();
+                for object in &group.objects {
+                    let kind = object_type(object);
+                    let owner = format!("object {} (`{}`)", object.id, kind);
+                    if object.rotation != 0.
+                        || object.gid.is_some()
+                        || object.ellipse
+                        || object.polygon.is_some()
+                        || object.text.is_some()
+                        || (object.polyline.is_some() && kind != "path")
+                    {
+                        return Err(error(format!(
+                            "{}: only rectangles, points and path polylines are supported, \
+                             without rotation",
+                            owner
+                        )));
+                    }
+                    let rect = Rect::new(
+                        object.x * scale.x,
+                        object.y * scale.y,
+                        object.width * scale.x,
+                        object.height * scale.y,
+                    );
+                    // What spawns stands at the middle of the object's bottom edge.
+                    let foot = vec2(rect.center().x, rect.bottom());
+                    let spawn = |size: f32| (foot.x - size / 2., foot.y - size);
+                    let no_properties =
+                        |owner: &str| check_properties(&object.properties, &[], owner);
+                    match kind {
+                        "player" if player.is_some() => {
+                            return Err(error(format!("{}: a second player spawn", owner)));
+                        }
+                        "player" => {
+                            no_properties(&owner)?;
+                            player = Some(spawn(PLAYER_SIZE));
+                        }
+                        "exit" if exit.is_some() => {
+                            return Err(error(format!("{}: a second exit", owner)));
+                        }
+                        "exit" => {
+                            no_properties(&owner)?;
+                            exit = Some((foot.x, foot.y));
+                        }
+                        "checkpoint" => {
+                            no_properties(&owner)?;
+                            level.checkpoints.push((foot.x, foot.y));
+                        }
+                        "key" => {
+                            no_properties(&owner)?;
+                            level.keys.push(spawn(KEY_SIZE));
+                        }
+                        "block" => {
+                            no_properties(&owner)?;
+                            level.blocks.push(spawn(BLOCK_SIZE));
+                        }
+                        "item" => {
+                            no_properties(&owner)?;
+                            level.items.push(spawn(ITEM_SIZE));
+                        }
+                        "baddie" => {
+                            if object
+                                .properties
+                                .iter()
+                                .any(|property| property.name == "kind")
+                            {
+                                return Err(error(format!(
+                                    "{}: baddie kinds are not supported, all baddies are alike",
+                                    owner
+                                )));
+                            }
+                            no_properties(&owner)?;
+                            level.baddies.push(spawn(BADDIE_SIZE));
+                        }
+                        "platform" => {
+                            let platform = import_platform(object, rect, scale, &paths, &owner)?;
+                            level.platforms.push(platform);
+                        }
+                        "path" => no_properties(&owner)?,
+                        "" => return Err(error(format!("{}: the object has no type", owner))),
+                        _ => return Err(error(format!("{}: unsupported object type", owner))),
+                    }
+                }
+            }
+            Layer::Image(layer) => {
+                return Err(error(format!(
+                    "layer `{}`: image layers are not supported",
+                    layer.name
+                )));
+            }
+            Layer::Group(layer) => {
+                return Err(error(format!(
+                    "layer `{}`: group layers are not supported",
+                    layer.name
+                )));
+            }
+        }
+    }
+
+    level.player = player.ok_or_else(|| error("missing `player` object".to_owned()))?;
+    level.exit = exit.ok_or_else(|| error("missing `exit` object".to_owned()))?;
+    Ok(level)
+}
+
+/// Returns the type of an object: its class in recent versions of Tiled, its type before.
+fn object_type(object: &Object) -> &str {
+    if object.class.is_empty() {
+        &object.kind
+    } else {
+        &object.class
+    }
+}
+
+/// Adds the tiles of a tile layer to the level, and the platforms made of its solid tiles.
+fn import_tile_layer(
+    layer: &TileLayer,
+    map: &Map,
+    firstgid: u32,
+    level: &mut LevelFile,
+) -> Result<(), ConfigError> {
+    let owner = format!("layer `{}`", layer.name);
+    if layer
+        .encoding
+        .as_deref()
+        .is_some_and(|encoding| encoding != "csv")
+    {
+        return Err(error(format!(
+            "{}: compressed or base64 tile data is not supported: save tile layers as CSV",
+            owner
+        )));
+    }
+    if layer.offsetx != 0. || layer.offsety != 0. {
+        return Err(error(format!("{}: layer offsets are not supported", owner)));
+    }
+    check_properties(&layer.properties, &["solidity"], &owner)?;
+    let solidity = solidity(&layer.properties, true, &owner)?;
+    let gids: Vec<u32> = layer
+        .data
+        .as_ref()
+        .and_then(|data| serde_json::from_value(data.clone()).ok())
+        .ok_or_else(|| {
+            error(format!(
+                "{}: the tile data must be an array of tile ids",
+                owner
+            ))
+        })?;
+    let count = map.width.checked_mul(map.height).ok_or_else(|| {
+        error(format!(
+            "{}: the {} x {} map is too large",
+            owner, map.width, map.height
+        ))
+    })?;
+    if gids.len() != count as usize {
+        return Err(error(format!(
+            "{}: {} tiles for a {} x {} map",
+            owner,
+            gids.len(),
+            map.width,
+            map.height
+        )));
+    }
+
+    let cell = LEVEL_GRID_CELL_SIZE;
+    for (row, gids) in gids.chunks(map.width as usize).enumerate() {
+        // The platform being extended along the row.
+        let mut run: Option<Rect> = None;
+        for (column, &gid) in gids.iter().chain([&0]).enumerate() {
+            let rect = Rect::new(column as f32 * cell, row as f32 * cell, cell, cell);
+            if gid == 0 {
+                if let (Some(rect), Some(solidity)) = (run.take(), solidity) {
+                    level.platforms.push(PlatformSpec {
+                        rect: Area(rect),
+                        solidity,
+                        path: None,
+                        hidden: true,
+                    });
+                }
+                continue;
+            }
+            if gid & FLIP_FLAGS != 0 {
+                return Err(error(format!(
+                    "{}: flipped or rotated tiles are not supported (row {}, column {})",
+                    owner,
+                    row + 1,
+                    column + 1
+                )));
+            }
+            let tile = gid
+                .checked_sub(firstgid)
+                .filter(|&tile| tile < TILESET_TILE_COUNT)
+                .ok_or_else(|| error(format!("{}: unknown tile id {}", owner, gid)))?;
+            level.tiles.push(TileSpec {
+                rect: Area(rect),
+                tile,
+            });
+            match &mut run {
+                Some(run) => run.w += cell,
+                None => run = Some(rect),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Creates the platform of a `platform` rectangle. If its `path` property points at a `path`
+/// polyline, the platform moves the way the polyline goes, wherever the polyline is drawn.
+fn import_platform(
+    object: &Object,
+    rect: Rect,
+    scale: Vec2,
+    paths: &HashMap<u32, &Object>,
+    owner: &str,
+) -> Result<PlatformSpec, ConfigError> {
+    let properties = &object.properties;
+    check_properties(
+        properties,
+        &["solidity", "path", "mode", "speed", "wait"],
+        owner,
+    )?;
+    let solidity = solidity(properties, false, owner)?.unwrap_or(Solidity::Solid);
+    let rect = Area::try_from((rect.x, rect.y, rect.w, rect.h))
+        .map_err(|message| error(format!("{}: {}", owner, message)))?;
+
+    let path = match properties.iter().find(|property| property.name == "path") {
+        None => None,
+        Some(property) => {
+            let path = property
+                .value
+                .as_u64()
+                .and_then(|id| paths.get(&(id as u32)))
+                .ok_or_else(|| error(format!("{}: `path` must point at a `path` object", owner)))?;
+            let points = path.polyline.as_deref().unwrap_or_default();
+            // The platform moves the way the polyline goes from its first point.
+            let first = points
+                .first()
+                .map_or(Vec2::ZERO, |point| vec2(point.x, point.y));
+            let to = points
+                .iter()
+                .skip(1)
+                .map(|point| {
+                    let offset = (vec2(point.x, point.y) - first) * scale;
+                    (rect.0.x + offset.x, rect.0.y + offset.y)
+                })
+                .collect();
+            let mode = match string_property(properties, "mode", owner)? {
+                None | Some("PingPong") => PathMode::PingPong,
+                Some("Linear") => PathMode::Linear,
+                Some("Loop") => PathMode::Loop,
+                Some(other) => {
+                    return Err(error(format!("{}: unknown path mode `{}`", owner, other)));
+                }
+            };
+            Some(PathSpec {
+                mode,
+                to,
+                speed: number_property(properties, "speed", owner)?,
+                wait: number_property(properties, "wait", owner)?,
+            })
+        }
+    };
+    Ok(PlatformSpec {
+        rect,
+        solidity,
+        path,
+        hidden: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILESET: &str = r#"{"firstgid": 1, "image": "world_tileset.png", "tilewidth": 16,
+        "tileheight": 16, "columns": 16}"#;
+    const SPAWNS: &str = r#"{"type": "objectgroup", "name": "spawns", "objects": [
+        {"id": 1, "type": "player", "x": 8, "y": 16, "point": true},
+        {"id": 2, "class": "exit", "x": 40, "y": 16, "point": true}]}"#;
+
+    /// Returns a 3 x 2 map made of `layers`, drawn with `tileset`.
+    fn map(layers: &[&str], tileset: &str) -> String {
+        format!(
+            r#"{{"orientation": "orthogonal", "width": 3, "height": 2, "tilewidth": 16,
+            "tileheight": 16, "layers": [{}], "tilesets": [{}]}}"#,
+            layers.join(", "),
+            tileset
+        )
+    }
+
+    #[test]
+    fn tiled_maps_become_levels_one_cell_per_tile() {
+        let ground = r#"{"type": "tilelayer", "name": "ground", "data": [0, 0, 0, 1, 1, 17]}"#;
+        let level = import(&map(&[ground, SPAWNS], TILESET)).unwrap();
+        let cell = LEVEL_GRID_CELL_SIZE;
+        assert_eq!(level.size, (3. * cell, 2. * cell));
+        assert_eq!(level.tiles.len(), 3);
+        assert_eq!(level.tiles[2].tile, 16);
+        // A row of solid tiles is one hidden platform, under the tiles that draw it.
+        assert_eq!(level.platforms.len(), 1);
+        assert_eq!(
+            level.platforms[0].rect,
+            Area(Rect::new(0., cell, 3. * cell, cell))
+        );
+        assert!(level.platforms[0].hidden);
+        assert_eq!(
+            level.player,
+            ((cell - PLAYER_SIZE) / 2., cell - PLAYER_SIZE)
+        );
+        assert_eq!(level.exit, (2.5 * cell, cell));
+
+        let garden = LevelFile::load("assets/levels/garden.tmj").unwrap();
+        assert_eq!((garden.keys.len(), garden.baddies.len()), (2, 1));
+        let moving = garden
+            .platforms
+            .iter()
+            .find(|platform| platform.path.is_some())
+            .unwrap();
+        assert_eq!(moving.solidity, Solidity::OneWay);
+        assert_eq!(
+            moving.path.as_ref().unwrap().to,
+            vec![(19. * cell, 6. * cell)]
+        );
+    }
+
+    #[test]
+    fn unsupported_map_features_are_explicit_errors() {
+        let message = |json: &str| import(json).unwrap_err().message;
+        let flipped =
+            r#"{"type": "tilelayer", "name": "ground", "data": [0, 0, 0, 1, 1, 2147483649]}"#;
+        assert_eq!(
+            message(&map(&[flipped, SPAWNS], TILESET)),
+            "layer `ground`: flipped or rotated tiles are not supported (row 2, column 3)"
+        );
+        let encoded = r#"{"type": "tilelayer", "name": "ground", "encoding": "base64",
+            "compression": "zlib", "data": "eJxjZGBgAAAADgAC"}"#;
+        assert!(message(&map(&[encoded, SPAWNS], TILESET)).contains("save tile layers as CSV"));
+        assert_eq!(
+            message(&map(&[SPAWNS], r#"{"firstgid": 1, "source": "world.tsx"}"#)),
+            "external tileset world.tsx is not supported: embed it in the map"
+        );
+        let ghost = r#"{"type": "objectgroup", "name": "baddies", "objects": [
+            {"id": 3, "type": "baddie", "x": 24, "y": 16, "point": true,
+             "properties": [{"name": "kind", "type": "string", "value": "ghost"}]}]}"#;
+        assert_eq!(
+            message(&map(&[SPAWNS, ghost], TILESET)),
+            "object 3 (`baddie`): baddie kinds are not supported, all baddies are alike"
+        );
+        let empty = r#"{"type": "tilelayer", "name": "ground", "data": []}"#;
+        let flat = map(&[empty, SPAWNS], TILESET).replace(r#""width": 3"#, r#""width": 0"#);
+        assert_eq!(
+            message(&flat),
+            "invalid map size 0 x 2: it must be positive"
+        );
+        let huge = map(&[empty, SPAWNS], TILESET).replace(
+            r#""width": 3, "height": 2"#,
+            r#""width": 65536, "height": 65536"#,
+        );
+        assert_eq!(
+            message(&huge),
+            "layer `ground`: the 65536 x 65536 map is too large"
+        );
+        let image = r#"{"type": "imagelayer", "name": "sky"}"#;
+        assert_eq!(
+            message(&map(&[SPAWNS, image], TILESET)),
+            "layer `sky`: image layers are not supported"
+        );
+
+        let err = import("{\n\"orientation\": \"orthogonal\",\n}").unwrap_err();
+        assert_eq!(err.line, Some(3));
+        assert_eq!(
+            LevelFile::load("map.tmx").unwrap_err().message,
+            "TMX maps are not supported: export the map from Tiled as JSON"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Timestep Module
+//!
+//! This module turns variable frame times into a whole number of fixed-length simulation
+//! steps, so that physics behaves the same regardless of the frame rate.
+
+/// Accumulates frame time and hands it out as fixed-length simulation steps.
+pub struct FixedTimestep {
+    step: f32,
+    max_steps_per_frame: u32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Creates a timestep running at `rate_hz` steps per second. At most
+    /// `max_steps_per_frame` steps are run for a single frame; any time beyond that is dropped
+    /// so that a long frame (e.g. a window drag) does not trigger a spiral of catch-up steps.
+    pub fn new(rate_hz: f32, max_steps_per_frame: u32) -> Self {
+        Self {
+            step: 1.0 / rate_hz,
+            max_steps_per_frame,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Returns the duration of a single simulation step, in seconds.
+    pub fn step_duration(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds the duration of the last frame and returns how many steps should be simulated.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps_per_frame {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        // Drop the time that could not be caught up with.
+        if steps == self.max_steps_per_frame {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+
+        steps
+    }
+
+    /// Returns how far the leftover time has progressed towards the next step, between 0 and 1.
+    /// This is used to interpolate rendering between the last two simulated states.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_partial_frames_into_whole_steps() {
+        let mut timestep = FixedTimestep::new(100.0, 8);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn clamps_catch_up_after_a_long_frame() {
+        let mut timestep = FixedTimestep::new(120.0, 8);
+        assert_eq!(timestep.advance(2.0), 8);
+        assert!(timestep.alpha() <= 1.0);
+        assert_eq!(timestep.advance(0.0), 1);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}
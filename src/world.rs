@@ -0,0 +1,1269 @@
+//! World Module
+//!
+//! This module holds the simulated game world (level, player and baddies) and advances it
+//! one step at a time. It does not read input, query the frame time or draw anything, so it
+//! can be stepped headlessly, for instance from tests.
+
+use crate::arena::{Arena, Handle};
+use crate::baddies::Baddie;
+use crate::blocks::{Block, BlockState};
+use crate::body::{Body, BodyOptions};
+use crate::campaign::CAMPAIGN;
+use crate::constants::{BLOCK_OFFSET, BLOCK_PUSH_SPEED, HELD_BLOCK_OFFSET};
+use crate::input::InputFrame;
+use crate::items::ItemState;
+use crate::level::Level;
+use crate::level_file::{LevelFile, point};
+use crate::physics::{self, Carry, Colliders, Side};
+use crate::player::{HeldObject, Player};
+use crate::score::{LevelTally, Score};
+use ::rand::SeedableRng;
+use ::rand::rngs::StdRng;
+use macroquad::prelude::*;
+
+/// Represents everything that is simulated during a level.
+pub struct World {
+    pub level: Level,
+    pub player: Player,
+    pub baddies: Arena<Baddie>,
+    /// Set by `step` when the player was hurt during that step: they touched a baddie or a
+    /// thrown item while vulnerable, or they were crushed.
+    pub player_hit: bool,
+    /// The points earned in this level so far.
+    pub score: Score,
+    /// How long the level has been played, in seconds of simulated time.
+    pub elapsed: f32,
+    /// The index of the level in the campaign.
+    pub level_index: usize,
+    /// The single source of randomness for baddie behaviour.
+    pub rng: StdRng,
+    /// The surfaces bodies collide with, rebuilt every step and kept up to date as blocks are
+    /// picked up, dropped and moved.
+    colliders: Colliders,
+}
+
+impl World {
+    /// Creates a new world for the first level of the campaign, generated from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self::for_level(seed, 0)
+    }
+
+    /// Creates a new world for the level at `level_index` in the campaign, loaded from its
+    /// level file with the player and baddies at their spawn points. Baddie behaviour is driven
+    /// by `seed`: the same seed and level always yield the same world.
+    pub fn for_level(seed: u64, level_index: usize) -> Self {
+        Self::from_file(&CAMPAIGN[level_index].level_file(), seed, level_index)
+    }
+
+    /// Creates a new world for `file`, played as the level at `level_index` of the run, with the
+    /// player and baddies at their spawn points and baddie behaviour driven by `seed`.
+    pub fn from_file(file: &LevelFile, seed: u64, level_index: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let level = Level::new(file);
+
+        let mut baddies = Arena::new();
+        for &position in &file.baddies {
+            baddies.insert(Baddie::new(point(position), &mut rng));
+        }
+
+        Self {
+            colliders: Colliders::new(&level),
+            level,
+            player: Player::new(point(file.player)),
+            baddies,
+            player_hit: false,
+            score: Score::new(),
+            elapsed: 0.0,
+            level_index,
+            rng,
+        }
+    }
+
+    /// Advances the world by `dt` seconds using the given input.
+    pub fn step(&mut self, input: &InputFrame, dt: f32) {
+        self.store_previous_positions();
+        self.elapsed += dt;
+        self.score.update(dt);
+        let player_crushed = self.update_platforms(dt);
+        self.update_player_and_collisions(input, dt);
+        self.update_world_objects(dt);
+        self.update_baddies_and_collisions(dt);
+        self.update_keys();
+        self.update_checkpoints();
+        self.player_hit = self.hurt_player(player_crushed);
+    }
+
+    /// Returns the number of keys the player has collected so far.
+    pub fn keys_collected(&self) -> u32 {
+        self.level.total_keys - self.level.keys.len() as u32
+    }
+
+    /// Returns whether the level is complete: the player walked into the exit once it opened.
+    pub fn is_complete(&self) -> bool {
+        self.level.exit_is_open() && self.level.exit.rect.overlaps(&self.player.body.rect())
+    }
+
+    /// Tallies the points of the level, with the bonuses for how fast it was finished and how
+    /// many lives are left.
+    pub fn tally(&self) -> LevelTally {
+        self.score.tally(self.elapsed, self.player.lives)
+    }
+
+    /// Remembers where every moving object was before this step, for render interpolation.
+    fn store_previous_positions(&mut self) {
+        self.player.body.store_previous_position();
+        for baddie in self.baddies.values_mut() {
+            baddie.body.store_previous_position();
+        }
+        for item in self.level.items.values_mut() {
+            item.body.store_previous_position();
+        }
+        for block in self.level.blocks.values_mut() {
+            block.body.store_previous_position();
+        }
+        for platform in &mut self.level.platforms {
+            platform.previous_position = platform.rect.point();
+        }
+    }
+
+    /// Moves the platforms along their paths, carrying the player, baddies, blocks and items
+    /// standing on them. Walls and solid surfaces hold riders back while the platform slides on,
+    /// but a rider that would be pushed into the ceiling or a solid surface is crushed instead: a
+    /// baddie is removed, a block or an item is left behind to fall, and `true` is returned if
+    /// the player was crushed.
+    fn update_platforms(&mut self, dt: f32) -> bool {
+        let mut player_crushed = false;
+        for (index, platform) in self.level.platforms.iter_mut().enumerate() {
+            let Some(path) = &mut platform.path else {
+                continue;
+            };
+            let from = platform.rect;
+            let motion = path.advance(from.point(), dt) - from.point();
+            if motion == Vec2::ZERO {
+                continue;
+            }
+            platform.rect = from.offset(motion);
+            self.colliders
+                .move_platform(index, platform.solidity, from, platform.rect);
+
+            if physics::stands_on(&self.player.body, &from)
+                && !physics::carry_rider(&mut self.player.body, motion, dt, &self.colliders)
+            {
+                player_crushed = true;
+            }
+
+            let mut crushed = Vec::new();
+            for (handle, baddie) in self.baddies.iter_mut() {
+                if physics::stands_on(&baddie.body, &from)
+                    && !physics::carry_rider(&mut baddie.body, motion, dt, &self.colliders)
+                {
+                    crushed.push(handle);
+                }
+            }
+            // What a crushed baddie held is let go of by `update_world_objects`.
+            for handle in crushed {
+                self.baddies.remove(handle);
+            }
+
+            // Each block is taken out of the colliders while it is carried, so that it does not
+            // crush itself.
+            for (handle, block) in self.level.blocks.iter_mut() {
+                if block.state == BlockState::Idle && physics::stands_on(&block.body, &from) {
+                    self.colliders.remove_block(handle);
+                    if !physics::carry_rider(&mut block.body, motion, dt, &self.colliders) {
+                        block.body.on_ground = false;
+                    }
+                    self.colliders.insert_block(handle, block.body.rect());
+                }
+            }
+            for item in self.level.items.values_mut() {
+                if item.state != ItemState::Hooked
+                    && physics::stands_on(&item.body, &from)
+                    && !physics::carry_rider(&mut item.body, motion, dt, &self.colliders)
+                {
+                    item.body.on_ground = false;
+                }
+            }
+        }
+        player_crushed
+    }
+
+    /// Handles the player's movement, interactions, and physics collisions.
+    fn update_player_and_collisions(&mut self, input: &InputFrame, dt: f32) {
+        // Down and jump together drop the player through the one-way platform it stands on.
+        if input.down && input.jump && self.player.body.on_ground {
+            self.player.body.dropping_through = self
+                .colliders
+                .one_way_platform_under(&self.player.body.rect());
+        }
+        let from = self.player.body.position;
+        self.player.update(input, dt);
+        // Player interactions can modify items and blocks, so it needs mutable access.
+        self.player
+            .process_interactions(&mut self.level.items, &mut self.level.blocks, input);
+
+        // The colliders are rebuilt after the interactions, so that a block the player just
+        // picked up is no longer solid. They are then updated incrementally for the rest of the step.
+        self.colliders.rebuild(&self.level);
+        let carry = self.player.carry(&self.level.items, &self.level.blocks);
+        let contacts = physics::move_body(
+            &mut self.player.body,
+            from,
+            &BodyOptions::CHARACTER,
+            carry,
+            &self.colliders,
+        );
+
+        // Walking into a block pushes it.
+        if let Some((block, side)) = contacts.block
+            && self.player.body.on_ground
+        {
+            let pushed = self.push_block(block, side, dt);
+            self.player.body.position.x += pushed;
+        }
+    }
+
+    /// Pushes a block sideways, along with the blocks stacked on it, and returns how far it went.
+    /// The higher the stack, the slower it goes. The block stops against walls, solid platforms
+    /// and other blocks; once moved, it is woken up so that it falls if it was pushed off a ledge.
+    fn push_block(&mut self, handle: Handle<Block>, side: Side, dt: f32) -> f32 {
+        // Gather the stack, from the pushed block up.
+        let mut stack = vec![handle];
+        let mut next = 0;
+        while next < stack.len() {
+            let Some(below) = self.level.blocks.get(stack[next]) else {
+                return 0.0;
+            };
+            let below = below.body.rect();
+            let above: Vec<_> = self
+                .level
+                .blocks
+                .iter()
+                .filter(|&(handle, block)| {
+                    block.state == BlockState::Idle
+                        && !stack.contains(&handle)
+                        && physics::stands_on(&block.body, &below)
+                })
+                .map(|(handle, _)| handle)
+                .collect();
+            stack.extend(above);
+            next += 1;
+        }
+
+        let speed = BLOCK_PUSH_SPEED / stack.len() as f32;
+        let colliders = &mut self.colliders;
+        let Some(block) = self.level.blocks.get_mut(handle) else {
+            return 0.0;
+        };
+        colliders.remove_block(handle);
+        let from = block.body.position;
+        block.body.velocity.x = if side == Side::Right { speed } else { -speed };
+        block.body.advance(dt);
+        let options = block.body_options();
+        physics::move_body(&mut block.body, from, &options, Carry::default(), colliders);
+        block.body.velocity.x = 0.0;
+        colliders.insert_block(handle, block.body.rect());
+        let pushed = block.body.position.x - from.x;
+
+        // The blocks on top follow, unless something stops them, in which case they fall off.
+        for &handle in &stack[1..] {
+            if let Some(block) = self.level.blocks.get_mut(handle) {
+                colliders.remove_block(handle);
+                physics::carry_rider(&mut block.body, vec2(pushed, 0.), dt, colliders);
+                block.body.on_ground = false;
+                colliders.insert_block(handle, block.body.rect());
+            }
+        }
+        pushed
+    }
+
+    /// Handles the updates and physics for all non-character objects in the world (items and blocks).
+    fn update_world_objects(&mut self, dt: f32) {
+        self.wake_unsupported_objects();
+        let colliders = &mut self.colliders;
+
+        for (handle, item) in self.level.items.iter_mut() {
+            if item.state != ItemState::Hooked {
+                if !item.body.on_ground {
+                    let from = item.body.position;
+                    item.update(dt);
+                    let options = item.body_options();
+                    physics::move_body(&mut item.body, from, &options, Carry::default(), colliders);
+                    // A thrown item that came to rest can be picked up again.
+                    if item.body.on_ground {
+                        item.state = ItemState::Idle;
+                    }
+                }
+            } else {
+                let is_held = self.player.held_object == Some(HeldObject::Item(handle))
+                    || self
+                        .baddies
+                        .values()
+                        .any(|baddie| baddie.held_item_id == Some(handle));
+                if !is_held {
+                    item.state = ItemState::Idle;
+                }
+            }
+        }
+
+        // Each falling block is taken out of the colliders while it moves, so that it does not
+        // collide with itself, and put back where it ends up for the blocks that follow. A thrown
+        // block stays out of them until it lands: it is not solid while it flies, but it stops
+        // against the solid blocks in its way.
+        for (handle, block) in self.level.blocks.iter_mut() {
+            if block.state != BlockState::Hooked {
+                if !block.body.on_ground {
+                    colliders.remove_block(handle);
+                    let from = block.body.position;
+                    block.update(dt);
+                    let options = block.body_options();
+                    let contacts = physics::move_body(
+                        &mut block.body,
+                        from,
+                        &options,
+                        Carry::default(),
+                        colliders,
+                    );
+                    if contacts.landed {
+                        block.state = BlockState::Idle;
+                    }
+                    if block.state == BlockState::Idle {
+                        colliders.insert_block(handle, block.body.rect());
+                    }
+                }
+            } else {
+                let is_held = self.player.held_object == Some(HeldObject::Block(handle))
+                    || self
+                        .baddies
+                        .values()
+                        .any(|baddie| baddie.grabbed_block_id == Some(handle));
+                if !is_held {
+                    block.state = BlockState::Idle;
+                    colliders.insert_block(handle, block.body.rect());
+                }
+            }
+        }
+    }
+
+    /// Wakes up the items and blocks resting on a block that was picked up, moved or removed, so
+    /// that they fall. A woken block wakes what rests on it in turn, so a whole stack falls at
+    /// once.
+    fn wake_unsupported_objects(&mut self) {
+        loop {
+            let unsupported: Vec<_> = self
+                .level
+                .blocks
+                .iter()
+                .filter(|(_, block)| {
+                    block.body.on_ground && !is_supported(&block.body, &self.level.blocks)
+                })
+                .map(|(handle, _)| handle)
+                .collect();
+            if unsupported.is_empty() {
+                break;
+            }
+            for handle in unsupported {
+                if let Some(block) = self.level.blocks.get_mut(handle) {
+                    block.body.on_ground = false;
+                }
+            }
+        }
+
+        for item in self.level.items.values_mut() {
+            if item.state != ItemState::Hooked
+                && item.body.on_ground
+                && !is_supported(&item.body, &self.level.blocks)
+            {
+                item.body.on_ground = false;
+            }
+        }
+    }
+
+    /// Handles baddie movement, interactions, and collisions, including their interactions with thrown items and blocks.
+    fn update_baddies_and_collisions(&mut self, dt: f32) {
+        let colliders = &mut self.colliders;
+
+        // --- Baddie Updates ---
+        for baddie in self.baddies.values_mut() {
+            let from = baddie.body.position;
+            baddie.update(dt, &mut self.rng);
+            baddie.process_interactions(&mut self.level.items, self.player.body.position);
+            let carry = baddie.carry(&self.level.items, &self.level.blocks);
+            let contacts = physics::move_body(
+                &mut baddie.body,
+                from,
+                &BodyOptions::CHARACTER,
+                carry,
+                colliders,
+            );
+            baddie.react_to_contacts(
+                &contacts,
+                &mut self.level.blocks,
+                &mut self.level.items,
+                colliders,
+                &mut self.rng,
+            );
+        }
+
+        // Update block positions for baddies that are holding them
+        for baddie in self.baddies.values() {
+            if let Some(block_id) = baddie.grabbed_block_id
+                && let Some(block) = self.level.blocks.get_mut(block_id)
+            {
+                let baddie_rect = baddie.body.rect();
+                let block_body = &mut block.body;
+
+                // Vertically align the block with the baddy, using the original offset.
+                block_body.position.y = baddie_rect.y - block_body.size.y + BLOCK_OFFSET;
+
+                // Horizontally position the block in front of the baddy with a small gap.
+                if baddie.facing_right {
+                    block_body.position.x = baddie_rect.right() + HELD_BLOCK_OFFSET;
+                } else {
+                    block_body.position.x =
+                        baddie_rect.left() - block_body.size.x - HELD_BLOCK_OFFSET;
+                }
+            }
+        }
+
+        // --- Baddie vs. Thrown Item Collisions ---
+        // When a thrown item hits a baddie, remove both. Removing from an arena leaves every
+        // other handle valid, so objects held by the survivors are unaffected.
+        let mut baddies_hit = Vec::new();
+        let mut items_hit = Vec::new();
+        let mut items_to_drop = Vec::new();
+
+        for (item_handle, item) in self.level.items.iter() {
+            if item.state == ItemState::Thrown {
+                for (baddie_handle, baddie) in self.baddies.iter() {
+                    // Check if the baddie hasn't already been marked for removal by another item
+                    if !baddies_hit.contains(&baddie_handle)
+                        && item.body.touched_during_step(&baddie.body)
+                    {
+                        baddies_hit.push(baddie_handle);
+                        items_hit.push(item_handle);
+
+                        // If the baddie was holding a block, drop it.
+                        if let Some(block_id) = baddie.grabbed_block_id
+                            && let Some(block) = self.level.blocks.get_mut(block_id)
+                        {
+                            block.state = BlockState::Idle;
+                        }
+
+                        // If the baddie was holding an item, drop it.
+                        if let Some(item_id) = baddie.held_item_id {
+                            items_to_drop.push(item_id);
+                        }
+
+                        // An item is consumed upon hitting a baddie and cannot hit another in the same frame.
+                        break;
+                    }
+                }
+            }
+        }
+
+        // --- Baddie vs. Thrown Block Collisions ---
+        // A thrown block destroys every baddie it hits or lands on, and flies on. Whatever those
+        // baddies held is let go of by the next step's object update.
+        for block in self.level.blocks.values() {
+            if block.state == BlockState::Thrown {
+                for (baddie_handle, baddie) in self.baddies.iter() {
+                    if !baddies_hit.contains(&baddie_handle)
+                        && block.body.touched_during_step(&baddie.body)
+                    {
+                        baddies_hit.push(baddie_handle);
+                    }
+                }
+            }
+        }
+
+        for item_id in items_to_drop {
+            if let Some(item) = self.level.items.get_mut(item_id) {
+                item.state = ItemState::Idle;
+            }
+        }
+
+        for baddie in baddies_hit {
+            self.baddies.remove(baddie);
+            self.score.baddie_killed();
+        }
+        for item in items_hit {
+            self.level.items.remove(item);
+        }
+    }
+
+    /// Hurts the player if they touched a baddie or a thrown item, unless they are still
+    /// invulnerable from the last hit, and takes a life from them if they were crushed. Returns
+    /// whether the player was hurt.
+    fn hurt_player(&mut self, crushed: bool) -> bool {
+        // Being crushed is deadly even while invulnerable: there is nowhere left to stand.
+        if crushed {
+            self.player.lose_life();
+            return true;
+        }
+        if self.player.is_invulnerable() {
+            return false;
+        }
+
+        // --- Player vs. Baddie Collision ---
+        let player_rect = self.player.body.rect();
+        let touched_baddie = self
+            .baddies
+            .values()
+            .map(|baddie| baddie.body.rect())
+            .find(|rect| player_rect.overlaps(rect));
+
+        // --- Player vs. Thrown Item Collision ---
+        let touched_thrown_item = self
+            .level
+            .items
+            .values()
+            .filter(|item| item.state == ItemState::Thrown)
+            .map(|item| item.body.rect())
+            .find(|rect| player_rect.overlaps(rect));
+
+        match touched_baddie.or(touched_thrown_item) {
+            Some(source) => {
+                self.player.take_hit(source.center().x);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes the checkpoints the player touches their respawn point.
+    fn update_checkpoints(&mut self) {
+        let player_rect = self.player.body.rect();
+        for checkpoint in &mut self.level.checkpoints {
+            if !checkpoint.reached && checkpoint.rect.overlaps(&player_rect) {
+                checkpoint.reached = true;
+                self.player.respawn_point = checkpoint.respawn_point(self.player.body.size);
+            }
+        }
+    }
+
+    /// Removes the keys the player is touching, counting them in the score.
+    fn update_keys(&mut self) {
+        let player_rect = self.player.body.rect();
+        let score = &mut self.score;
+        self.level.keys.retain(|_, key| {
+            let touched = key.rect.overlaps(&player_rect);
+            if touched {
+                score.key_collected();
+            }
+            !touched
+        });
+    }
+}
+
+/// Returns whether a resting body is still held up by the block it came to rest on: the block
+/// is still there, lying idle where it was. A body resting on anything else is always held up.
+fn is_supported(body: &Body, blocks: &Arena<Block>) -> bool {
+    let Some(handle) = body.resting_on else {
+        return true;
+    };
+    blocks.get(handle).is_some_and(|block| {
+        block.state == BlockState::Idle
+            && block.body.on_ground
+            && physics::stands_on(body, &block.body.rect())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{
+        BADDIE_SIZE, BLOCK_SIZE, ITEM_SIZE, ITEM_THROW_SPEED, LIFE_BONUS, PLAYER_LIVES,
+        PLAYER_MAX_HEALTH, PLAYER_SPEED,
+    };
+    use crate::items::Item;
+    use crate::level::{Checkpoint, Platform};
+    use crate::physics::Solidity;
+    use crate::platforms::{PathMode, PlatformPath, Waypoint};
+
+    const DT: f32 = 1.0 / 60.0;
+    const SEED: u64 = 42;
+
+    /// Returns the first level of the campaign, as loaded from its file.
+    fn first_level() -> Level {
+        Level::new(&CAMPAIGN[0].level_file())
+    }
+
+    /// Returns a world built from an ASCII level grid.
+    fn arena(grid: &str) -> World {
+        World::from_file(&LevelFile::from_grid(grid).unwrap(), SEED, 0)
+    }
+
+    #[test]
+    fn player_settles_on_the_ground_without_input() {
+        let mut world = World::new(SEED);
+        // Baddies would knock the player around.
+        world.baddies = Arena::new();
+        let spawn = world.player.body.position;
+        for _ in 0..600 {
+            world.step(&InputFrame::default(), DT);
+        }
+        assert!(world.player.body.on_ground);
+        assert_eq!(world.player.body.rect().bottom(), world.level.ground.top());
+        assert_eq!(world.player.body.position.x, spawn.x);
+    }
+
+    #[test]
+    fn holding_right_moves_the_player_right() {
+        let mut world = World::new(SEED);
+        let spawn = world.player.body.position;
+        let input = InputFrame {
+            right: true,
+            ..Default::default()
+        };
+        for _ in 0..6 {
+            world.step(&input, DT);
+        }
+        assert!(world.player.body.position.x > spawn.x);
+        assert!(world.player.facing_right);
+    }
+
+    #[test]
+    fn jump_leaves_the_ground_and_lands_again() {
+        let mut world = World::new(SEED);
+        world.step(&InputFrame::default(), DT);
+        assert!(world.player.body.on_ground);
+
+        let jump = InputFrame {
+            jump: true,
+            ..Default::default()
+        };
+        world.step(&jump, DT);
+        assert!(!world.player.body.on_ground);
+        assert!(world.player.body.velocity.y < 0.0);
+
+        for _ in 0..120 {
+            world.step(&InputFrame::default(), DT);
+        }
+        assert!(world.player.body.on_ground);
+    }
+
+    #[test]
+    fn down_and_jump_drop_the_player_through_a_one_way_platform() {
+        let mut world = World::new(SEED);
+        world.baddies = Arena::new();
+        let platform = world
+            .level
+            .platforms
+            .iter()
+            .find(|platform| platform.solidity == Solidity::OneWay)
+            .unwrap()
+            .rect;
+        let body = &mut world.player.body;
+        body.position = vec2(platform.x + 10., platform.top() - body.size.y);
+        world.step(&InputFrame::default(), DT);
+        assert!(world.player.body.on_ground);
+
+        let drop = InputFrame {
+            down: true,
+            jump: true,
+            ..Default::default()
+        };
+        world.step(&drop, DT);
+        for _ in 0..30 {
+            world.step(&InputFrame::default(), DT);
+        }
+        assert!(world.player.body.rect().top() > platform.bottom());
+    }
+
+    /// Returns a world without baddies whose only platform moves between two points.
+    fn world_with_moving_platform(from: Vec2, to: Vec2) -> World {
+        let mut world = World::new(SEED);
+        world.baddies = Arena::new();
+        let waypoint = |position| Waypoint {
+            position,
+            wait: 0.0,
+        };
+        world.level.platforms = vec![Platform::moving(
+            vec2(150., 20.),
+            Solidity::OneWay,
+            PlatformPath::new(
+                vec![waypoint(from), waypoint(to)],
+                PathMode::PingPong,
+                120.0,
+            ),
+        )];
+        world.colliders = Colliders::new(&world.level);
+        world
+    }
+
+    #[test]
+    fn moving_platforms_carry_riders_who_keep_their_speed_when_jumping_off() {
+        let mut world = world_with_moving_platform(vec2(300., 1200.), vec2(700., 1200.));
+        let body = &mut world.player.body;
+        body.position = vec2(350., 1200. - body.size.y);
+        world.step(&InputFrame::default(), DT);
+        assert!(world.player.body.on_ground);
+        let offset = world.player.body.position.x - world.level.platforms[0].rect.x;
+
+        for _ in 0..60 {
+            world.step(&InputFrame::default(), DT);
+        }
+        let platform = world.level.platforms[0].rect;
+        assert!(platform.x > 400.);
+        assert!(world.player.body.on_ground);
+        assert_eq!(world.player.body.rect().bottom(), platform.top());
+        assert!((world.player.body.position.x - platform.x - offset).abs() < 0.01);
+
+        let jump = InputFrame {
+            jump: true,
+            ..Default::default()
+        };
+        world.step(&jump, DT);
+        world.step(&InputFrame::default(), DT);
+        assert!(!world.player.body.on_ground);
+        assert!((world.player.body.velocity.x - 120.).abs() < 0.01);
+    }
+
+    #[test]
+    fn riders_pushed_against_a_wall_are_held_back() {
+        let wall = first_level().left_wall.right();
+        let mut world = world_with_moving_platform(vec2(300., 1200.), vec2(wall, 1200.));
+        let body = &mut world.player.body;
+        // The player stands on the very end of the platform, sticking out towards the wall.
+        body.position = vec2(300. - body.size.x + 10., 1200. - body.size.y);
+        // The platform reaches the wall after a little over two seconds.
+        for _ in 0..125 {
+            world.step(&InputFrame::default(), DT);
+            assert!(!world.player_hit);
+        }
+        // The platform slid under the player, who was held back by the wall and still rides it.
+        let platform = world.level.platforms[0].rect;
+        assert!(platform.left() < wall + 1.);
+        assert!((world.player.body.rect().left() - wall).abs() < 0.01);
+        assert!(physics::stands_on(&world.player.body, &platform));
+        assert_eq!(world.player.lives, PLAYER_LIVES);
+    }
+
+    #[test]
+    fn riders_pinned_under_the_ceiling_are_crushed() {
+        let ceiling = first_level().ceiling.bottom();
+        let mut world = world_with_moving_platform(vec2(300., 1200.), vec2(300., ceiling));
+        world.level.blocks = Arena::new();
+        world.colliders = Colliders::new(&world.level);
+        let body = &mut world.player.body;
+        body.position = vec2(350., 1200. - body.size.y);
+        body.on_ground = true;
+        let mut crushed = false;
+        for _ in 0..720 {
+            world.step(&InputFrame::default(), DT);
+            crushed |= world.player_hit;
+        }
+        assert!(crushed);
+        assert!(world.player.lives < PLAYER_LIVES);
+        assert!(world.player.body.rect().top() >= ceiling);
+    }
+
+    /// Returns a world with flat ground, no baddies and idle blocks at the given positions.
+    fn world_with_blocks(positions: &[Vec2]) -> (World, Vec<Handle<Block>>) {
+        let mut world = World::new(SEED);
+        world.baddies = Arena::new();
+        world.level.platforms.clear();
+        world.level.slopes.clear();
+        world.level.blocks = Arena::new();
+        let blocks = positions
+            .iter()
+            .map(|&position| world.level.blocks.insert(Block::new(position)))
+            .collect();
+        (world, blocks)
+    }
+
+    #[test]
+    fn walking_into_a_block_pushes_it_along_with_the_blocks_stacked_on_it() {
+        let floor = first_level().ground.top();
+        let (mut world, blocks) = world_with_blocks(&[
+            vec2(400., floor - BLOCK_SIZE),
+            vec2(400., floor - 2. * BLOCK_SIZE),
+        ]);
+        let right = InputFrame {
+            right: true,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            world.step(&right, DT);
+        }
+
+        let bottom = world.level.blocks.get(blocks[0]).unwrap().body.clone();
+        let top = world.level.blocks.get(blocks[1]).unwrap().body.clone();
+        // The stack moved, but slower than the player walks.
+        assert!(bottom.position.x > 420.);
+        assert!(bottom.position.x < 400. + PLAYER_SPEED * 0.5);
+        assert!((top.position.x - bottom.position.x).abs() < 0.01);
+        assert_eq!(top.rect().bottom(), bottom.rect().top());
+        assert!((world.player.body.rect().right() - bottom.rect().left()).abs() < 0.01);
+    }
+
+    #[test]
+    fn blocks_stop_against_walls_and_fall_off_ledges() {
+        let level = first_level();
+        let floor = level.ground.top();
+        let against_wall = level.right_wall.left() - BLOCK_SIZE;
+        let (mut world, blocks) = world_with_blocks(&[
+            vec2(against_wall, floor - BLOCK_SIZE),
+            vec2(420., 1200. - BLOCK_SIZE),
+        ]);
+        world.level.platforms.push(Platform::new(
+            Rect::new(300., 1200., 200., 20.),
+            Solidity::Solid,
+        ));
+
+        // Push the block at the wall...
+        let body = &mut world.player.body;
+        body.position = vec2(against_wall - body.size.x - 5., floor - body.size.y);
+        let right = InputFrame {
+            right: true,
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            world.step(&right, DT);
+        }
+        let block = world.level.blocks.get(blocks[0]).unwrap();
+        assert_eq!(block.body.position.x, against_wall);
+        assert_eq!(world.player.body.rect().right(), against_wall);
+
+        // ...then the block on the platform, off its edge.
+        let body = &mut world.player.body;
+        body.position = vec2(310., 1200. - body.size.y);
+        for _ in 0..240 {
+            world.step(&right, DT);
+        }
+        let block = world.level.blocks.get(blocks[1]).unwrap();
+        assert!(block.body.on_ground);
+        assert_eq!(block.body.rect().bottom(), floor);
+    }
+
+    #[test]
+    fn objects_fall_when_the_block_under_them_is_picked_up() {
+        let floor = first_level().ground.top();
+        let (mut world, blocks) = world_with_blocks(&[
+            vec2(600., floor - BLOCK_SIZE),
+            vec2(600., floor - 2. * BLOCK_SIZE),
+        ]);
+        world.level.items = Arena::new();
+        let item = world
+            .level
+            .items
+            .insert(Item::new(vec2(610., floor - 2. * BLOCK_SIZE - ITEM_SIZE)));
+        for _ in 0..10 {
+            world.step(&InputFrame::default(), DT);
+        }
+        let top = world.level.blocks.get(blocks[1]).unwrap();
+        assert!(top.body.on_ground);
+        assert_eq!(top.body.resting_on, Some(blocks[0]));
+        assert_eq!(
+            world.level.items.get(item).unwrap().body.resting_on,
+            Some(blocks[1])
+        );
+
+        // The player takes the bottom block away: the stack on it comes down.
+        world.level.blocks.get_mut(blocks[0]).unwrap().state = BlockState::Hooked;
+        world.player.held_object = Some(HeldObject::Block(blocks[0]));
+        for _ in 0..60 {
+            world.step(&InputFrame::default(), DT);
+        }
+        let top = world.level.blocks.get(blocks[1]).unwrap();
+        assert!(top.body.on_ground);
+        assert_eq!(top.body.rect().bottom(), floor);
+        let item = world.level.items.get(item).unwrap();
+        assert!(item.body.on_ground);
+        assert_eq!(item.body.rect().bottom(), top.body.rect().top());
+    }
+
+    /// Puts a block in the player's hands.
+    fn hand_block_to_player(world: &mut World, handle: Handle<Block>) {
+        world.level.blocks.get_mut(handle).unwrap().state = BlockState::Hooked;
+        world.player.held_object = Some(HeldObject::Block(handle));
+        world.step(&InputFrame::default(), DT);
+    }
+
+    #[test]
+    fn thrown_blocks_fly_a_short_arc_and_destroy_the_baddies_they_hit() {
+        let floor = first_level().ground.top();
+        let (mut world, blocks) = world_with_blocks(&[vec2(600., floor - BLOCK_SIZE)]);
+        hand_block_to_player(&mut world, blocks[0]);
+        let held = world.level.blocks.get(blocks[0]).unwrap().body.rect();
+        let mut baddie = Baddie::new(vec2(held.right() + 5., floor - BADDIE_SIZE), &mut world.rng);
+        baddie.facing_right = false;
+        let baddie = world.baddies.insert(baddie);
+
+        let throw = InputFrame {
+            grab_throw: true,
+            ..Default::default()
+        };
+        world.step(&throw, DT);
+        let block = world.level.blocks.get(blocks[0]).unwrap();
+        assert!(block.state == BlockState::Thrown);
+        assert!(block.body.velocity.length() < ITEM_THROW_SPEED);
+
+        for _ in 0..120 {
+            world.step(&InputFrame::default(), DT);
+        }
+        assert!(world.baddies.get(baddie).is_none());
+        assert_eq!(world.score.kills, 1);
+        let block = world.level.blocks.get(blocks[0]).unwrap();
+        assert!(block.state == BlockState::Idle);
+        assert!(block.body.on_ground);
+        assert_eq!(block.body.rect().bottom(), floor);
+        // An item thrown the same way would have flown about twice as far.
+        let range = block.body.position.x - held.x;
+        assert!(
+            range > BLOCK_SIZE && range < 3. * BLOCK_SIZE,
+            "range {range}"
+        );
+    }
+
+    #[test]
+    fn thrown_blocks_stop_against_a_stack() {
+        let floor = first_level().ground.top();
+        let (mut world, blocks) = world_with_blocks(&[vec2(600., floor - BLOCK_SIZE)]);
+        let stack_x = world.player.body.rect().right() + 2. * BLOCK_SIZE;
+        for height in 1..=3 {
+            let position = vec2(stack_x, floor - height as f32 * BLOCK_SIZE);
+            world.level.blocks.insert(Block::new(position));
+        }
+        hand_block_to_player(&mut world, blocks[0]);
+        let throw = InputFrame {
+            grab_throw: true,
+            ..Default::default()
+        };
+        world.step(&throw, DT);
+        for _ in 0..120 {
+            world.step(&InputFrame::default(), DT);
+        }
+        let block = world.level.blocks.get(blocks[0]).unwrap();
+        assert!(block.state == BlockState::Idle);
+        assert!(block.body.on_ground);
+        assert!(block.body.rect().right() <= stack_x);
+    }
+
+    #[test]
+    fn down_and_grab_drops_a_held_block_at_the_players_feet() {
+        let floor = first_level().ground.top();
+        let (mut world, blocks) = world_with_blocks(&[vec2(600., floor - BLOCK_SIZE)]);
+        hand_block_to_player(&mut world, blocks[0]);
+        let held_x = world.level.blocks.get(blocks[0]).unwrap().body.position.x;
+        let drop = InputFrame {
+            down: true,
+            grab_throw: true,
+            ..Default::default()
+        };
+        world.step(&drop, DT);
+        for _ in 0..60 {
+            world.step(&InputFrame::default(), DT);
+        }
+        let block = world.level.blocks.get(blocks[0]).unwrap();
+        assert!(block.state == BlockState::Idle);
+        assert_eq!(block.body.position.x, held_x);
+        assert_eq!(block.body.rect().bottom(), floor);
+    }
+
+    #[test]
+    fn a_thrown_item_kills_a_baddie_two_cells_away() {
+        let mut world = arena(
+            "
+#########
+#PI  E D#
+#########
+",
+        );
+        let (baddie, _) = world.baddies.iter().next().unwrap();
+        let walk_and_grab = InputFrame {
+            right: true,
+            grab_throw: true,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            if world.player.held_object.is_some() {
+                break;
+            }
+            world.step(&walk_and_grab, DT);
+        }
+        assert!(matches!(
+            world.player.held_object,
+            Some(HeldObject::Item(_))
+        ));
+        let throw = InputFrame {
+            grab_throw: true,
+            ..Default::default()
+        };
+        world.step(&throw, DT);
+        for _ in 0..60 {
+            world.step(&InputFrame::default(), DT);
+        }
+        assert!(world.baddies.get(baddie).is_none());
+        assert_eq!(world.score.kills, 1);
+    }
+
+    #[test]
+    fn a_hit_knocks_the_player_back_and_leaves_them_briefly_invulnerable() {
+        let floor = first_level().ground.top();
+        let (mut world, _) = world_with_blocks(&[]);
+        world.step(&InputFrame::default(), DT);
+        let player = world.player.body.rect();
+        let mut baddie = Baddie::new(
+            vec2(player.right() - 5., floor - BADDIE_SIZE),
+            &mut world.rng,
+        );
+        baddie.facing_right = false;
+        world.baddies.insert(baddie);
+
+        world.step(&InputFrame::default(), DT);
+        assert!(world.player_hit);
+        assert_eq!(world.player.health, PLAYER_MAX_HEALTH - 1);
+        assert!(world.player.body.velocity.x < 0.);
+        assert!(world.player.is_invulnerable());
+
+        // The baddie walks through the blinking player without hurting them again.
+        for _ in 0..30 {
+            world.step(&InputFrame::default(), DT);
+            assert!(!world.player_hit);
+        }
+        assert_eq!(world.player.health, PLAYER_MAX_HEALTH - 1);
+        assert_eq!(world.player.lives, PLAYER_LIVES);
+    }
+
+    #[test]
+    fn losing_a_life_respawns_the_player_at_the_last_checkpoint() {
+        let floor = first_level().ground.top();
+        let (mut world, _) = world_with_blocks(&[]);
+        world.level.checkpoints = vec![Checkpoint::new(vec2(800., floor))];
+        let checkpoint = world.level.checkpoints[0].respawn_point(world.player.body.size);
+        world.player.body.position = checkpoint + vec2(100., 0.);
+        world.step(&InputFrame::default(), DT);
+        assert_ne!(world.player.respawn_point, checkpoint);
+        world.player.body.position = checkpoint;
+        world.step(&InputFrame::default(), DT);
+        assert_eq!(world.player.respawn_point, checkpoint);
+
+        // The last hit of the life, far from the checkpoint, sends the player back there.
+        world.player.body.position.x = 1500.;
+        world.player.health = 1;
+        world.level.items = Arena::new();
+        let mut item = Item::new(world.player.body.position);
+        item.state = ItemState::Thrown;
+        world.level.items.insert(item);
+        world.step(&InputFrame::default(), DT);
+        assert_eq!(world.player.lives, PLAYER_LIVES - 1);
+        assert_eq!(world.player.health, PLAYER_MAX_HEALTH);
+        assert_eq!(world.player.body.position, checkpoint);
+        assert!(world.player.is_invulnerable());
+    }
+
+    #[test]
+    fn walking_into_the_exit_once_every_key_is_collected_completes_the_level() {
+        let (mut world, _) = world_with_blocks(&[]);
+        let exit = world.level.exit.rect.point();
+        let visit = |world: &mut World, position: Vec2| {
+            world.player.body.position = position;
+            world.player.body.velocity = Vec2::ZERO;
+            world.step(&InputFrame::default(), DT);
+        };
+
+        // The exit is locked until the last key is collected.
+        visit(&mut world, exit);
+        assert!(!world.is_complete());
+        let keys: Vec<Rect> = world.level.keys.values().map(|key| key.rect).collect();
+        for (collected, key) in keys.iter().enumerate() {
+            assert!(!world.level.exit_is_open());
+            visit(&mut world, key.point());
+            assert_eq!(world.score.keys, collected as u32 + 1);
+        }
+        assert!(world.level.exit_is_open());
+        assert!(!world.is_complete());
+        visit(&mut world, exit);
+        assert!(world.is_complete());
+        let tally = world.tally();
+        assert_eq!(tally.key_points, world.score.points());
+        assert!(tally.time_bonus > 0);
+        assert_eq!(tally.lives_bonus, PLAYER_LIVES * LIFE_BONUS);
+    }
+
+    #[test]
+    fn thousands_of_steps_keep_bodies_inside_the_level() {
+        // Baddie behaviour depends on the seed, so several runs are checked, after every step.
+        for seed in [SEED, 1, 2, 3] {
+            let mut world = World::new(seed);
+            let bounds = Rect::new(0., 0., world.level.size.x, world.level.size.y);
+            for frame in 0..5000 {
+                let input = InputFrame {
+                    left: frame % 400 < 200,
+                    right: frame % 400 >= 200,
+                    down: frame % 135 == 0,
+                    jump: frame % 45 == 0,
+                    grab_throw: frame % 90 == 0,
+                };
+                world.step(&input, DT);
+
+                let bodies = std::iter::once(&world.player.body)
+                    .chain(world.baddies.values().map(|baddie| &baddie.body))
+                    .chain(world.level.items.values().map(|item| &item.body))
+                    .chain(world.level.blocks.values().map(|block| &block.body));
+                for body in bodies {
+                    assert!(
+                        bounds.overlaps(&body.rect()),
+                        "seed {}, step {}: body at {} left the level",
+                        seed,
+                        frame,
+                        body.position
+                    );
+                }
+            }
+            assert!(world.keys_collected() <= world.level.total_keys);
+        }
+    }
+
+    #[test]
+    fn removing_a_thrown_item_keeps_other_held_items() {
+        let mut world = World::new(SEED);
+        let items = world
+            .level
+            .items
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        let baddies = world
+            .baddies
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        let (thrown, held) = (items[0], items[1]);
+
+        // The first item is thrown at the first baddie while the second baddie holds another item.
+        let target = world.baddies.get(baddies[0]).unwrap().body.position;
+        let item = world.level.items.get_mut(thrown).unwrap();
+        item.state = ItemState::Thrown;
+        item.body.position = target;
+        let holder = world.baddies.get_mut(baddies[1]).unwrap();
+        holder.held_item_id = Some(held);
+        holder.item_hold_timer = 10.0;
+        world.level.items.get_mut(held).unwrap().state = ItemState::Hooked;
+
+        world.update_baddies_and_collisions(DT);
+
+        assert!(world.level.items.get(thrown).is_none());
+        assert!(world.baddies.get(baddies[0]).is_none());
+        let holder = world.baddies.get(baddies[1]).unwrap();
+        assert_eq!(holder.held_item_id, Some(held));
+        assert!(world.level.items.get(held).unwrap().state == ItemState::Hooked);
+    }
+
+    #[test]
+    fn same_seed_produces_the_same_run() {
+        let mut first = World::new(SEED);
+        let mut second = World::new(SEED);
+        for frame in 0..1000 {
+            let input = InputFrame {
+                right: frame % 200 < 100,
+                jump: frame % 30 == 0,
+                ..Default::default()
+            };
+            first.step(&input, DT);
+            second.step(&input, DT);
+        }
+        assert_eq!(first.player.body.position, second.player.body.position);
+        assert_eq!(first.baddies.len(), second.baddies.len());
+        for (a, b) in first.baddies.values().zip(second.baddies.values()) {
+            assert_eq!(a.body.position, b.body.position);
+        }
+        for (a, b) in first
+            .level
+            .blocks
+            .values()
+            .zip(second.level.blocks.values())
+        {
+            assert_eq!(a.body.position, b.body.position);
+        }
+    }
+
+    #[test]
+    fn different_seeds_make_baddies_behave_differently() {
+        let mut first = World::new(1);
+        let mut second = World::new(2);
+        // The layout comes from the level file, whatever the seed.
+        for (a, b) in first
+            .level
+            .blocks
+            .values()
+            .zip(second.level.blocks.values())
+        {
+            assert_eq!(a.body.position, b.body.position);
+        }
+        for _ in 0..600 {
+            first.step(&InputFrame::default(), DT);
+            second.step(&InputFrame::default(), DT);
+        }
+        let same_baddies = first
+            .baddies
+            .values()
+            .zip(second.baddies.values())
+            .all(|(a, b)| a.body.position == b.body.position);
+        assert!(!same_baddies);
+    }
+
+    /// Returns the grid of a level crowded with `floors` floors of baddies and blocks, 32 of
+    /// each per floor.
+    fn crowded_grid(floors: usize) -> String {
+        let mut grid = format!("{:<64}\n", "P");
+        for _ in 0..floors {
+            grid += &" ".repeat(64);
+            grid += "\n";
+            grid += &"EB".repeat(32);
+            grid += "\n";
+            grid += &"#".repeat(64);
+            grid += "\n";
+        }
+        grid + &format!("{:>64}\n", "D") + &"#".repeat(64)
+    }
+
+    /// Steps crowded levels with hundreds of baddies and blocks, then four times as many,
+    /// printing the time a step takes. Thanks to the broadphase, it grows with the number of
+    /// bodies rather than with its square. Run with
+    /// `cargo test --release benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark_stepping_crowded_levels() {
+        const STEPS: u32 = 300;
+        let walk = InputFrame {
+            right: true,
+            ..Default::default()
+        };
+
+        let mut times = Vec::new();
+        for floors in [4, 16] {
+            let mut world = arena(&crowded_grid(floors));
+            let bodies = world.baddies.len() + world.level.blocks.len();
+            let start = std::time::Instant::now();
+            for _ in 0..STEPS {
+                world.step(&walk, DT);
+            }
+            let time = start.elapsed() / STEPS;
+            println!(
+                "{} baddies and {} blocks: {:?} per step",
+                world.baddies.len(),
+                world.level.blocks.len(),
+                time
+            );
+            times.push((bodies, time));
+
+            // Timings vary with the machine's load, so only the simulation is checked.
+            let bounds = Rect::new(0., 0., world.level.size.x, world.level.size.y);
+            assert!(
+                world
+                    .baddies
+                    .values()
+                    .all(|baddie| bounds.overlaps(&baddie.body.rect()))
+            );
+        }
+        let ((few, few_time), (many, many_time)) = (times[0], times[1]);
+        println!(
+            "{:.1}x the bodies take {:.1}x the time",
+            many as f64 / few as f64,
+            many_time.as_secs_f64() / few_time.as_secs_f64()
+        );
+    }
+}